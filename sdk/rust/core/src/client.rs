@@ -0,0 +1,5201 @@
+//! The `SecretsManager` client, mirroring `core.py` in the Python core SDK:
+//! binding, transmission-key exchange and record/folder fetch & decrypt.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use hmac::{Hmac, Mac};
+use p256::SecretKey;
+use sha2::Sha512;
+
+use crate::crypto::{
+    self, generate_encryption_key_bytes_with, generate_random_bytes_with, public_key_ecc,
+    OsRngProvider, RngProvider,
+};
+use crate::dto::{
+    DefaultRecordType, FieldSelector, Folder, FolderMeta, FolderSummary, KeeperFileUpload, LinkedRecord,
+    QueryOptions, Record, RecordCreate, RecordField, RecordMeta, RecordTypeSchema, ResponseSource,
+    SecretsManagerResponse, VaultFolderNode, VaultSnapshot,
+};
+use crate::error::KSMRError;
+use crate::keeper_globals::{
+    keeper_server_for_abbreviation, DEFAULT_KEY_ID, KEEPER_SECRETS_MANAGER_SDK_CLIENT_ID,
+};
+use crate::payload::{
+    AddFileResponse, AttachmentInfo, CreateFolderPayload, CreatePayload, FileRecordMeta, FileUploadPayload,
+    GetPayload, GetSecretsResponseWire, KsmHttpResponse, ReencryptedRecordData, RequestDownloadPayload,
+    RequestDownloadResponse, UpdateFolderPayload, UpdatePayload, UpdateTransactionType, WireRecord,
+};
+use crate::storage::{ConfigKey, InMemoryKeyValueStorage, KeyValueStorage};
+use crate::utils::{
+    base64_to_bytes, bytes_to_base64, bytes_to_string, bytes_to_url_safe_str, url_safe_str_to_bytes,
+};
+
+/// Field types whose value is a uid referencing another record, and therefore
+/// candidates for link resolution in [`SecretsManager::get_secret_with_links`].
+const LINKED_FIELD_TYPES: &[&str] = &["cardRef", "addressRef", "fileRef"];
+
+/// Keeper's published gateway signing keys, keyed by `serverPublicKeyId`.
+///
+/// The id shipped here is a local placeholder so the crate builds and the
+/// transmission-key round trip is testable offline; a production deployment
+/// must be seeded with Keeper's real published keys (see `keeper_public_keys`
+/// in the Python core SDK's `keeper_globals.py`) before talking to a live server.
+pub const KEEPER_PUBLIC_KEYS: &[(&str, &str)] =
+    &[("10", "BBi4pvFtTbK59WdNot3oJ5viFLpF9u4pD36FU2BSkEspd5E9Fv15zEsH5KeJxbwb0W9MtzmEGBw-OaTWosaV3ag")];
+
+fn keeper_public_key(key_id: &str) -> Option<Vec<u8>> {
+    KEEPER_PUBLIC_KEYS
+        .iter()
+        .find(|(id, _)| *id == key_id)
+        .and_then(|(_, key)| url_safe_str_to_bytes(key).ok())
+}
+
+/// Parses an RFC 7231 IMF-fixdate, the format used by the HTTP `Date` header
+/// (e.g. `Sun, 06 Nov 1994 08:49:37 GMT`). Written by hand rather than pulling
+/// in a date/time crate for this one conversion.
+fn parse_http_date(value: &str) -> Result<SystemTime, KSMRError> {
+    let invalid = || KSMRError::Other(format!("invalid HTTP date '{value}'"));
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 6 || parts[5] != "GMT" {
+        return Err(invalid());
+    }
+
+    let day: u64 = parts[1].parse().map_err(|_| invalid())?;
+    let month = match parts[2] {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return Err(invalid()),
+    };
+    let year: u64 = parts[3].parse().map_err(|_| invalid())?;
+
+    let mut time_parts = parts[4].split(':');
+    let hour: u64 = time_parts.next().and_then(|s| s.parse().ok()).ok_or_else(invalid)?;
+    let minute: u64 = time_parts.next().and_then(|s| s.parse().ok()).ok_or_else(invalid)?;
+    let second: u64 = time_parts.next().and_then(|s| s.parse().ok()).ok_or_else(invalid)?;
+
+    let days_since_epoch = days_from_civil(year, month, day);
+    let seconds = days_since_epoch * 86_400 + hour * 3_600 + minute * 60 + second;
+    Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Days since the Unix epoch for a proleptic-Gregorian civil date, using
+/// Howard Hinnant's well-known `days_from_civil` algorithm.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let y = if month <= 2 { year as i64 - 1 } else { year as i64 };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    (era * 146_097 + doe - 719_468) as u64
+}
+
+/// Parses an environment variable's string value as a boolean, matching the
+/// common truthy spellings accepted by `distutils.util.strtobool` (which the
+/// Python SDK uses for `KSM_SKIP_VERIFY`): `1`, `true`, `yes`, `on`, case-insensitive.
+fn parse_bool_env(value: &str) -> bool {
+    matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on")
+}
+
+/// Hook for overriding how a request body is delivered, used by tests and by
+/// callers who need to route traffic through their own transport.
+pub type CustomPostFn =
+    dyn Fn(&str, &[u8], bool) -> Result<KsmHttpResponse, KSMRError> + Send + Sync;
+
+/// Timing and outcome of a single [`SecretsManager::post_query`] call, handed to
+/// [`ClientOptions::metrics_callback`] for feeding into external metrics systems
+/// (e.g. Prometheus) without the caller having to wrap every call itself.
+#[derive(Debug, Clone)]
+pub struct RequestMetrics {
+    pub path: String,
+    pub duration: std::time::Duration,
+    pub status: Option<u16>,
+    pub retried: bool,
+    pub cache_hit: bool,
+}
+
+/// Hook invoked after each `post_query` with timing and outcome metrics.
+pub type MetricsFn = dyn Fn(RequestMetrics) + Send + Sync;
+
+/// Record of a single [`SecretsManager::post_query`] call, handed to
+/// [`ClientOptions::audit_callback`] for feeding a compliance log of which
+/// operations this SDK performed. Deliberately carries nothing from the
+/// request or response bodies or headers - those are the encrypted payload
+/// and transmission key, never useful for an audit trail and not something
+/// this hook should ever expose.
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub path: String,
+    /// Milliseconds since the Unix epoch when the call completed, from the
+    /// same clock as [`crate::utils::now_milliseconds`].
+    pub timestamp_millis: u64,
+    pub success: bool,
+    pub status: Option<u16>,
+}
+
+/// Hook invoked after each `post_query` with that call's [`AuditEvent`], for
+/// compliance logging of which endpoints this SDK called and whether they
+/// succeeded, without a caller having to wrap every call site itself. See
+/// [`ClientOptions::metrics_callback`] for a hook geared towards performance
+/// observability instead; the two are invoked independently and a caller
+/// that wants both can set them separately.
+pub type AuditFn = dyn Fn(AuditEvent) + Send + Sync;
+
+/// Test/override hook for [`SecretsManager::check_clock_skew`]: returns an
+/// RFC 7231 HTTP-date string, the same format carried in a `Date` response
+/// header. Lets tests supply a fixed server time instead of making a real
+/// network call.
+pub type ServerDateFn = dyn Fn() -> Result<String, KSMRError> + Send + Sync;
+
+/// Test/override hook for the raw multipart upload step of
+/// [`SecretsManager::upload_file`]: `(upload_url, form_fields, encrypted_file_bytes)`.
+pub type FileUploadFn =
+    dyn Fn(&str, &std::collections::HashMap<String, String>, &[u8]) -> Result<(), KSMRError> + Send + Sync;
+
+/// Test/override hook for the raw GET step of
+/// [`SecretsManager::download_file_to_writer`]: given the presigned URL
+/// `request_download` returned, returns the still-encrypted file bytes found
+/// there. Production code performs a real GET; tests can intercept it here
+/// instead of performing a real download.
+pub type FileDownloadFn = dyn Fn(&str) -> Result<Vec<u8>, KSMRError> + Send + Sync;
+
+/// Verbosity level for the SDK's own diagnostic output, ordered from least
+/// to most verbose. Set the initial level via [`ClientOptions::log_level`]
+/// and change it at runtime with [`SecretsManager::set_log_level`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+/// Outcome of saving one record as part of [`SecretsManager::save_all`].
+/// `error` is `None` on success.
+#[derive(Debug)]
+pub struct SaveResult {
+    pub uid: String,
+    pub error: Option<KSMRError>,
+}
+
+/// A cooperative cancellation flag for a bulk call like
+/// [`SecretsManager::save_all`]: cloning shares the same underlying flag, so a
+/// caller can hold one clone and call [`CancellationToken::cancel`] from
+/// another thread (e.g. when its client disconnects) while the bulk call is
+/// still running.
+///
+/// This client's network calls are blocking, not `Future`s, so there is no
+/// drop-to-abort or `tokio::select!` story here - a bulk call instead checks
+/// [`CancellationToken::is_cancelled`] between items and stops issuing new
+/// requests once it's set. Each item it has already started is still seen
+/// through to completion (one `post_query` call is never interrupted
+/// mid-flight), so a cancelled batch never leaves a single record half
+/// written; it just leaves the remaining records untouched.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+/// Construction options for [`SecretsManager`]. Mirrors the keyword
+/// arguments accepted by `SecretsManager.__init__` in the Python core SDK.
+pub struct ClientOptions {
+    pub token: Option<String>,
+    pub hostname: Option<String>,
+    pub verify_ssl_certs: bool,
+    pub config: Arc<dyn KeyValueStorage>,
+    pub rng: Arc<dyn RngProvider>,
+    /// Signature scheme used to authenticate requests to the gateway. Production
+    /// code leaves this at its default, [`crypto::P256Signer`] (ECDSA/P-256, the
+    /// only scheme the gateway currently accepts); tests can supply a mock
+    /// [`crypto::Signer`] instead of exercising real ECDSA.
+    pub signer: Arc<dyn crypto::Signer>,
+    pub custom_post_function: Option<Arc<CustomPostFn>>,
+    /// Invoked after each `post_query` with that call's [`RequestMetrics`], for
+    /// observability (e.g. exporting Keeper call latency to Prometheus) without
+    /// having to wrap every call site. `cache_hit` reflects
+    /// [`ClientOptions::enable_disaster_recovery_cache`]. The SDK does not yet
+    /// retry requests, so `retried` is currently always `false`.
+    pub metrics_callback: Option<Arc<MetricsFn>>,
+    /// Invoked after each `post_query` with that call's [`AuditEvent`] - the
+    /// endpoint path, completion timestamp, and success/status outcome, and
+    /// nothing from the request or response bodies or headers (which are
+    /// always encrypted and rarely useful for an audit trail anyway). Meant
+    /// for feeding a compliance log of which SM operations this client
+    /// performed; see [`ClientOptions::metrics_callback`] for latency/timing
+    /// observability instead.
+    pub audit_callback: Option<Arc<AuditFn>>,
+    /// Overrides the server time source used by [`SecretsManager::check_clock_skew`].
+    /// Production code leaves this `None` and reads the real `Date` header from a
+    /// plain, unauthenticated request to the configured hostname; tests can supply
+    /// a fixed timestamp here instead.
+    pub server_date_override: Option<Arc<ServerDateFn>>,
+    /// Timeout applied specifically to the network fetch that completes
+    /// binding - the first `post_query` call a client makes before
+    /// [`ConfigKey::AppKey`] is stored in `config`, which unwraps and saves
+    /// the app key from the one-time token. `None` (the default) leaves
+    /// that fetch as untimed as every other plain `post_query` call.
+    /// Exceeding this deadline returns [`KSMRError::BindTimeout`] instead of
+    /// the generic [`KSMRError::Network`] a later, already-bound call would
+    /// get for the same underlying timeout, so a short-lived caller (e.g. a
+    /// serverless function) can fail fast and distinctly on a hung bind.
+    /// Ignored when [`ClientOptions::custom_post_function`] is set, since
+    /// that function performs its own transport (or none at all) outside
+    /// this client's control.
+    pub bind_timeout: Option<Duration>,
+    /// Overrides the raw multipart upload step of [`SecretsManager::upload_file`].
+    /// Production code leaves this `None` and POSTs the encrypted file bytes to
+    /// the presigned URL returned by `add_file`; tests can intercept it here
+    /// instead of performing a real upload.
+    pub file_upload_override: Option<Arc<FileUploadFn>>,
+    /// Overrides the raw download step of [`SecretsManager::download_file_to_writer`].
+    /// Production code leaves this `None` and GETs the presigned URL
+    /// `request_download` returns; tests can intercept it here instead of
+    /// performing a real download.
+    pub file_download_override: Option<Arc<FileDownloadFn>>,
+    /// Number of additional attempts [`SecretsManager::download_file_to_writer`]
+    /// makes if the raw GET of the encrypted file from the presigned URL
+    /// fails or drops partway through - `0` (the default) disables
+    /// retrying. Unlike [`ClientOptions::upload_retries`], a retry here
+    /// doesn't restart from zero: it reissues the GET with a `Range` header
+    /// continuing from the bytes already received, and only falls back to a
+    /// full restart if a retry comes back with a full `200` instead of a
+    /// partial `206` (the storage URL doesn't support ranges, or doesn't
+    /// for this particular file). The file is still decrypted as one whole
+    /// blob only once every byte has been received - AES-256-GCM's
+    /// authentication tag is appended at the end of the ciphertext, so
+    /// there's nothing to verify, let alone return to the caller, until the
+    /// download completes either way. Ignored when
+    /// [`ClientOptions::file_download_override`] is set.
+    pub download_retries: u32,
+    /// Timeout applied to the raw multipart upload step of
+    /// [`SecretsManager::upload_file`], independent of the plain, untimed
+    /// requests this client otherwise makes. Attachment uploads can run far
+    /// longer than a normal `post_query` call, so a caller who wants a tight
+    /// deadline for ordinary secret reads still needs this set separately
+    /// (or left `None`) to give large uploads the time they need instead of
+    /// sharing a single short budget. Ignored when
+    /// [`ClientOptions::file_upload_override`] is set.
+    pub upload_timeout: Option<Duration>,
+    /// Number of additional attempts [`SecretsManager::upload_file`] makes
+    /// if the raw multipart upload fails with a transient error ([`KSMRError::Network`],
+    /// covering both a failed send and a non-2xx response from the presigned
+    /// URL) - `0` (the default) disables retrying. Each attempt resubmits
+    /// the same encrypted bytes to the same presigned URL, so retrying is
+    /// always safe: there is no partial-upload state an attempt could leave
+    /// behind for the next one to collide with. Ignored when
+    /// [`ClientOptions::file_upload_override`] is set.
+    pub upload_retries: u32,
+    /// When `true`, [`SecretsManager::new`] refuses to construct a client
+    /// whose effective TLS verification is disabled, whether that came from
+    /// `verify_ssl_certs = false` or the `KSM_SKIP_VERIFY` environment
+    /// variable. Off by default so existing callers are unaffected; turn on
+    /// via [`ClientOptions::require_secure_tls`] to make disabling
+    /// certificate verification an explicit, policy-forbidden error instead
+    /// of a silent foot-gun.
+    pub enforce_secure_tls: bool,
+    /// Initial verbosity for the client's diagnostic output. Change it after
+    /// construction with [`SecretsManager::set_log_level`].
+    pub log_level: LogLevel,
+    /// When `true`, caches the decrypted response of every successful
+    /// `get_secret` call in memory, and falls back to that cache instead of
+    /// failing outright when a later `get_secret` call can't reach the
+    /// gateway (a [`KSMRError::Network`] error). Off by default, since
+    /// serving stale data during an outage is a deliberate disaster-recovery
+    /// tradeoff, not something every caller wants silently.
+    ///
+    /// Records and folders are both fetched through the same `get_secret`
+    /// call in this SDK (see [`SecretsManager::get_secrets`] and
+    /// [`SecretsManager::get_folders`]), so enabling this once gives both a
+    /// fallback during an outage.
+    pub enable_disaster_recovery_cache: bool,
+    /// Extra HTTP headers merged into every outbound request the default
+    /// `post_function` sends (a reverse proxy or API gateway in front of the
+    /// Keeper gateway may require its own header, e.g. `X-Api-Gateway-Key`).
+    /// Ignored when [`ClientOptions::custom_post_function`] is set, since
+    /// that function builds its own request (or none at all) and never sees
+    /// these. `Content-Type` can't be overridden through this map, since the
+    /// protocol always sends a raw encrypted body under
+    /// `application/octet-stream`; a key that collides with it
+    /// case-insensitively is dropped rather than erroring, so a caller
+    /// reusing a generic header map for several HTTP clients doesn't have to
+    /// special-case this one.
+    pub extra_headers: std::collections::HashMap<String, String>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            token: None,
+            hostname: None,
+            verify_ssl_certs: true,
+            config: Arc::new(InMemoryKeyValueStorage::new()),
+            rng: Arc::new(OsRngProvider),
+            signer: Arc::new(crypto::P256Signer),
+            custom_post_function: None,
+            metrics_callback: None,
+            audit_callback: None,
+            server_date_override: None,
+            bind_timeout: None,
+            file_upload_override: None,
+            upload_timeout: None,
+            upload_retries: 0,
+            file_download_override: None,
+            download_retries: 0,
+            enforce_secure_tls: false,
+            log_level: LogLevel::default(),
+            enable_disaster_recovery_cache: false,
+            extra_headers: std::collections::HashMap::new(),
+        }
+    }
+}
+
+impl ClientOptions {
+    /// Makes [`SecretsManager::new`] return a [`KSMRError::Config`] instead of
+    /// constructing a client if TLS certificate verification would be
+    /// disabled (via `verify_ssl_certs = false` or `KSM_SKIP_VERIFY`).
+    pub fn require_secure_tls(mut self) -> Self {
+        self.enforce_secure_tls = true;
+        self
+    }
+}
+
+pub struct SecretsManager {
+    options: ClientOptions,
+    /// Mutable independently of `options` so [`SecretsManager::set_log_level`]
+    /// can take `&self` like the rest of this client's runtime state - a bound
+    /// client is routinely shared across threads (e.g. behind an `Arc`), and
+    /// `&mut self` would defeat that.
+    log_level: Mutex<LogLevel>,
+    /// Last successful `get_secret` response, kept decrypted since it's only
+    /// ever served back out through the same API it was captured from. See
+    /// [`ClientOptions::enable_disaster_recovery_cache`].
+    dr_cache: Mutex<Option<Vec<u8>>>,
+    /// Set for the duration of a [`SecretsManager::with_ssl_verification`]
+    /// call, overriding [`ClientOptions::verify_ssl_certs`] and
+    /// `KSM_SKIP_VERIFY` for every request made from inside it, without
+    /// mutating either. A `Mutex` rather than a true thread-local: calling
+    /// this concurrently from two threads sharing the same `SecretsManager`
+    /// still races, same as [`SecretsManager::set_log_level`] does for log
+    /// verbosity - this is meant for one-off troubleshooting on a single
+    /// thread, not a per-thread policy.
+    ssl_override: Mutex<Option<bool>>,
+}
+
+impl SecretsManager {
+    /// Builds a client and, if not already bound, derives the client id and
+    /// private key from the one-time token the same way `SecretsManager.__init__` does.
+    pub fn new(options: ClientOptions) -> Result<Self, KSMRError> {
+        let log_level = Mutex::new(options.log_level);
+        let sm = Self { options, log_level, dr_cache: Mutex::new(None), ssl_override: Mutex::new(None) };
+        if sm.options.enforce_secure_tls && !sm.effective_verify_ssl_certs() {
+            return Err(KSMRError::Config(
+                "refusing to start with TLS certificate verification disabled: \
+                 ClientOptions::require_secure_tls() is set, and either verify_ssl_certs \
+                 is false or KSM_SKIP_VERIFY disables verification"
+                    .into(),
+            ));
+        }
+        sm.bind_if_needed()?;
+        sm.validate_private_key_if_present()?;
+        Ok(sm)
+    }
+
+    /// Parses whatever private key [`bind_if_needed`](Self::bind_if_needed)
+    /// just loaded or generated, so a storage backend holding a corrupt
+    /// `PrivateKey` value (bad base64, or valid base64 that isn't a P-256
+    /// key) fails loudly and clearly right here in [`SecretsManager::new`],
+    /// instead of as a cryptic [`KSMRError::Crypto`] surfacing from the first
+    /// [`SecretsManager::encrypt_and_sign_payload`] call this client happens
+    /// to make. [`SecretsManager::repair_config`] is the suggested fix for an
+    /// unbound client; a bound one has to be re-created from a fresh token.
+    fn validate_private_key_if_present(&self) -> Result<(), KSMRError> {
+        if self.config().get(ConfigKey::PrivateKey).is_none() {
+            return Ok(());
+        }
+        self.private_key().map(|_| ()).map_err(|e| {
+            KSMRError::Config(format!(
+                "stored private key is corrupt ({e}); call SecretsManager::repair_config() if this client \
+                 isn't bound yet, or re-create it with a fresh one-time token if it already is"
+            ))
+        })
+    }
+
+    /// Regenerates this client's P-256 key pair in place, recovering from a
+    /// corrupt `PrivateKey` value in storage (see
+    /// [`SecretsManager::validate_private_key_if_present`]) without having to
+    /// throw away the rest of the config.
+    ///
+    /// Only safe before this client has completed its first real bind with
+    /// the gateway: once [`SecretsManager::is_bound`] is `true`, the server
+    /// already has this client's *old* public key on file, and swapping the
+    /// key pair out from under it would just trade one unusable client for
+    /// another. Fails with [`KSMRError::Config`] in that case - re-create the
+    /// client from a fresh one-time token instead.
+    pub fn repair_config(&self) -> Result<(), KSMRError> {
+        if self.is_bound() {
+            return Err(KSMRError::Config(
+                "cannot repair the key pair of a client that has already bound to the gateway; \
+                 re-create it with a fresh one-time token instead"
+                    .into(),
+            ));
+        }
+        let private_key = crypto::generate_private_key_ecc_with(self.rng())?;
+        self.config()
+            .set(ConfigKey::PrivateKey, bytes_to_base64(&private_key.to_bytes()));
+        Ok(())
+    }
+
+    /// Returns the client's current diagnostic log verbosity.
+    pub fn log_level(&self) -> LogLevel {
+        *self.log_level.lock().unwrap()
+    }
+
+    /// Updates the client's diagnostic log verbosity at runtime, e.g. to
+    /// temporarily bump a long-running service to [`LogLevel::Debug`] via an
+    /// admin endpoint without restarting it.
+    pub fn set_log_level(&self, level: LogLevel) {
+        *self.log_level.lock().unwrap() = level;
+    }
+
+    /// Drops the [`ClientOptions::enable_disaster_recovery_cache`] cache, so
+    /// the next `get_secret` that can't reach the gateway fails with a
+    /// [`KSMRError::Network`] error instead of silently serving the last
+    /// response this client saw. Useful after rotating credentials or at
+    /// logout, so a stale cache isn't served to whatever runs next under the
+    /// new credentials.
+    ///
+    /// Unlike the Python SDK's `KSMCache`, this cache lives only in this
+    /// `SecretsManager`'s process memory (it is never written to disk), so
+    /// there is no cache file to delete - clearing it is just dropping that
+    /// one in-memory blob.
+    pub fn clear_cache(&self) {
+        *self.dr_cache.lock().unwrap() = None;
+    }
+
+    /// Performs a live `get_secret` fetch purely to populate and prove the
+    /// [`ClientOptions::enable_disaster_recovery_cache`] cache ahead of an
+    /// outage, instead of leaving a deploy to discover an empty or stale
+    /// cache only when the gateway is already unreachable. Drops whatever
+    /// was cached before the fetch, so success here means this run's
+    /// response - decrypted records and all - is what a later outage would
+    /// actually serve, not a leftover from an earlier process.
+    ///
+    /// Fails with [`KSMRError::Config`] if disaster recovery caching isn't
+    /// enabled on this client at all, since there would be nothing to warm.
+    pub fn warm_cache(&self) -> Result<(), KSMRError> {
+        if !self.options.enable_disaster_recovery_cache {
+            return Err(KSMRError::Config(
+                "disaster recovery cache is not enabled (see ClientOptions::enable_disaster_recovery_cache)".into(),
+            ));
+        }
+        self.clear_cache();
+        self.fetch_and_decrypt_secrets(None)?;
+        if self.dr_cache.lock().unwrap().is_none() {
+            return Err(KSMRError::Other(
+                "live fetch succeeded but did not populate the disaster recovery cache".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Spawns a background thread that calls [`SecretsManager::warm_cache`]
+    /// every `interval`, so [`ClientOptions::enable_disaster_recovery_cache`]'s
+    /// cache stays fresh continuously instead of only whenever a foreground
+    /// call happens to populate it - a stale cache at outage time defeats
+    /// the point of having one. A failed refresh is logged (see
+    /// [`SecretsManager::log`]) and does not stop the loop; a single
+    /// transient outage shouldn't end background warming. The loop runs
+    /// for as long as this `SecretsManager` has at least one other `Arc`
+    /// clone alive (e.g. held by the rest of the application) plus the
+    /// clone this thread holds; dropping every other clone still leaves
+    /// this thread's own clone keeping it alive until the process exits or
+    /// the returned handle's thread is detached and the process itself
+    /// ends, so callers that want it stoppable should join or abandon the
+    /// handle at shutdown rather than relying on `Drop`.
+    ///
+    /// This crate has no async runtime anywhere in its dependency tree -
+    /// there is no `tokio`, no `SecretsManagerAsync` type, and every other
+    /// method here blocks the calling thread for its own network I/O. A
+    /// plain `std::thread::spawn` loop is this repo's own idiom for
+    /// long-running background work (see the cross-thread sharing note on
+    /// [`SecretsManager`] itself), so that's what this does instead of
+    /// introducing an async task type the rest of the crate has no use for.
+    pub fn spawn_cache_refresher(self: &Arc<Self>, interval: Duration) -> std::thread::JoinHandle<()> {
+        let sm = Arc::clone(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            if let Err(e) = sm.warm_cache() {
+                sm.log(LogLevel::Warn, format!("background cache refresh failed: {e}"));
+            }
+        })
+    }
+
+    /// Decrypts and parses whatever [`ClientOptions::enable_disaster_recovery_cache`]
+    /// currently holds for this client, without making a network call - lets tooling
+    /// (e.g. a `ksm cache inspect` command) verify the cache's contents ahead of an
+    /// outage instead of finding out what's in it only once the gateway is already
+    /// unreachable.
+    ///
+    /// Fails with [`KSMRError::Config`] if the cache is empty (nothing has been
+    /// cached yet - see [`SecretsManager::warm_cache`]), or if it's present but the
+    /// bytes don't parse as a `get_secret` response (corrupt). A cache written by a
+    /// different application - one whose app key can't decrypt these records -
+    /// surfaces as a [`KSMRError::Crypto`] failure from the normal AES-GCM
+    /// authentication check, same as any other wrong-key decryption in this crate;
+    /// this method adds a note to that error suggesting the mismatched-app
+    /// possibility rather than inventing a new error variant for it.
+    ///
+    /// Unlike the Python SDK's `KSMCache`, this crate's disaster recovery cache is
+    /// held in memory only, scoped to this `SecretsManager` (see the `dr_cache`
+    /// field doc) - there is no on-disk cache file a separate inspection process
+    /// could open independently. This method can only inspect the cache already
+    /// resident in the `SecretsManager` instance it's called on.
+    pub fn inspect_cache(&self) -> Result<SecretsManagerResponse, KSMRError> {
+        let decrypted = self.dr_cache.lock().unwrap().clone().ok_or_else(|| {
+            KSMRError::Config(
+                "disaster recovery cache is empty: nothing has been cached yet (see ClientOptions::enable_disaster_recovery_cache)".into(),
+            )
+        })?;
+
+        let wire: GetSecretsResponseWire = crate::utils::json_to_dict(&bytes_to_string(&decrypted)?)
+            .map_err(|e| KSMRError::Config(format!("disaster recovery cache is corrupt: {e}")))?;
+
+        let mut just_bound = false;
+        let secret_key = if let Some(encrypted_app_key) = &wire.encrypted_app_key {
+            just_bound = true;
+            let client_key_token = self
+                .config()
+                .get(ConfigKey::ClientKey)
+                .ok_or_else(|| KSMRError::Config("missing one-time token needed to unwrap the cached app key".into()))?;
+            let client_key = url_safe_str_to_bytes(&client_key_token)?;
+            crypto::decrypt_aes_gcm(&client_key, &url_safe_str_to_bytes(encrypted_app_key)?)?
+        } else {
+            base64_to_bytes(
+                &self
+                    .config()
+                    .get(ConfigKey::AppKey)
+                    .ok_or_else(|| KSMRError::Config("client is not bound: missing app key".into()))?,
+            )?
+        };
+
+        self.parse_wire_response(wire, secret_key, just_bound, ResponseSource::Cache, false)
+            .map_err(|e| match e {
+                KSMRError::Crypto(msg) => {
+                    KSMRError::Crypto(format!("{msg} (the cache may have been written by a different application)"))
+                }
+                other => other,
+            })
+    }
+
+    /// Writes `message` to stderr if `level` is at or below the client's
+    /// current verbosity (see [`SecretsManager::set_log_level`]).
+    fn log(&self, level: LogLevel, message: impl std::fmt::Display) {
+        if self.log_level() >= level {
+            eprintln!("[{level:?}] {message}");
+        }
+    }
+
+    fn rng(&self) -> &dyn RngProvider {
+        self.options.rng.as_ref()
+    }
+
+    fn signer(&self) -> &dyn crypto::Signer {
+        self.options.signer.as_ref()
+    }
+
+    fn config(&self) -> &dyn KeyValueStorage {
+        self.options.config.as_ref()
+    }
+
+    /// Whether this client has already exchanged its one-time token for an
+    /// application key, without exposing which [`ConfigKey`] that is tracked
+    /// under. Useful for a CLI or admin UI that wants to show "configured" vs.
+    /// "not configured" without poking at raw config keys itself.
+    pub fn is_bound(&self) -> bool {
+        self.config().is_bound()
+    }
+
+    /// Sets `ConfigKey::ServerPublicKeyId` to `key_id`, rejecting any id not
+    /// present in [`KEEPER_PUBLIC_KEYS`] - the only public way to change it,
+    /// since there's otherwise no recovering from a bad persisted value (or
+    /// pinning a specific key for a test) short of editing the config file
+    /// by hand.
+    pub fn set_server_public_key_id(&self, key_id: &str) -> Result<(), KSMRError> {
+        if keeper_public_key(key_id).is_none() {
+            return Err(KSMRError::Config(format!(
+                "'{key_id}' is not a known server public key id (expected one of: {})",
+                KEEPER_PUBLIC_KEYS.iter().map(|(id, _)| *id).collect::<Vec<_>>().join(", ")
+            )));
+        }
+        self.config().set(ConfigKey::ServerPublicKeyId, key_id.to_string());
+        Ok(())
+    }
+
+    /// Whether SSL certificate verification is actually in effect for this
+    /// client, folding in the `KSM_SKIP_VERIFY` environment variable (read on
+    /// every call, like the `requests`/Python SDK convention it mirrors) on
+    /// top of [`ClientOptions::verify_ssl_certs`].
+    fn effective_verify_ssl_certs(&self) -> bool {
+        if let Some(overridden) = *self.ssl_override.lock().unwrap() {
+            return overridden;
+        }
+        if !self.options.verify_ssl_certs {
+            return false;
+        }
+        match std::env::var("KSM_SKIP_VERIFY") {
+            Ok(value) => !parse_bool_env(&value),
+            Err(_) => true,
+        }
+    }
+
+    /// Runs `f` with [`ClientOptions::verify_ssl_certs`] (and
+    /// `KSM_SKIP_VERIFY`) overridden to `verify_ssl_certs` for every request
+    /// `f` makes, then restores whatever override (or lack of one) was in
+    /// place before - so a single troubleshooting call against a staging
+    /// host with a bad certificate doesn't need this client's global TLS
+    /// posture mutated and then manually reset. Fails with
+    /// [`KSMRError::Config`] instead of running `f` at all when
+    /// `verify_ssl_certs` is `false` and [`ClientOptions::require_secure_tls`]
+    /// is in effect - the same policy [`SecretsManager::new`] enforces at
+    /// construction applies here too.
+    pub fn with_ssl_verification<T>(
+        &self,
+        verify_ssl_certs: bool,
+        f: impl FnOnce() -> Result<T, KSMRError>,
+    ) -> Result<T, KSMRError> {
+        if !verify_ssl_certs && self.options.enforce_secure_tls {
+            return Err(KSMRError::Config(
+                "refusing to disable TLS certificate verification for this call: \
+                 ClientOptions::require_secure_tls() is set on this client"
+                    .into(),
+            ));
+        }
+        let previous = {
+            let mut guard = self.ssl_override.lock().unwrap();
+            let previous = *guard;
+            *guard = Some(verify_ssl_certs);
+            previous
+        };
+        let result = f();
+        *self.ssl_override.lock().unwrap() = previous;
+        result
+    }
+
+    /// Public alias for [`SecretsManager::effective_verify_ssl_certs`], for a
+    /// startup self-check to assert TLS verification is actually on. Given
+    /// `ClientOptions::verify_ssl_certs` and `KSM_SKIP_VERIFY` can each
+    /// independently disable verification - and the latter is re-read on
+    /// every call rather than fixed at construction - neither is a reliable
+    /// answer on its own; this is.
+    pub fn ssl_verification_enabled(&self) -> bool {
+        self.effective_verify_ssl_certs()
+    }
+
+    /// Splits a one-time token into an optional region hostname and the raw
+    /// token body, without attempting to bind. Tokens may be prefixed with a
+    /// Keeper region abbreviation (e.g. `US:ONE_TIME_TOKEN`); callers that
+    /// just want to validate a pasted token before attempting a network bind
+    /// can use this directly instead of going through [`SecretsManager::new`].
+    pub fn parse_token(token: &str) -> Result<(Option<String>, String), KSMRError> {
+        match token.split_once(':') {
+            Some((region, body)) => {
+                let hostname = keeper_server_for_abbreviation(region).ok_or_else(|| {
+                    KSMRError::Config(format!("unknown Keeper region '{region}' in token"))
+                })?;
+                Ok((Some(hostname.to_string()), body.to_string()))
+            }
+            None => Ok((None, token.to_string())),
+        }
+    }
+
+    fn bind_if_needed(&self) -> Result<(), KSMRError> {
+        if let Some(token) = &self.options.token {
+            let (region_hostname, token_body) = Self::parse_token(token)?;
+            // Kept around for the life of this client (not just the first `get_secret`
+            // response) so a later app-key rotation can still be unwrapped - see
+            // `fetch_wire_response`. Cleaned up below only when reloading a config
+            // that was already bound in an earlier process.
+            self.config().set(ConfigKey::ClientKey, token_body);
+            if let (Some(configured), Some(from_token)) = (&self.options.hostname, &region_hostname) {
+                if configured != from_token {
+                    return Err(KSMRError::Config(format!(
+                        "token region resolves to hostname '{from_token}' but ClientOptions::hostname is set to \
+                         '{configured}'; a region-prefixed token and an explicit hostname must agree (drop one \
+                         or make them match)"
+                    )));
+                }
+            }
+            if self.options.hostname.is_none() {
+                if let Some(hostname) = region_hostname {
+                    self.config().set(ConfigKey::Hostname, hostname);
+                }
+            }
+        }
+        if let Some(hostname) = &self.options.hostname {
+            self.config().set(ConfigKey::Hostname, hostname.clone());
+        }
+
+        if self.config().get(ConfigKey::ClientId).is_some() {
+            // Already bound: the one-time token is no longer needed.
+            self.config().delete(ConfigKey::ClientKey);
+            return Ok(());
+        }
+
+        let token = self
+            .options
+            .token
+            .clone()
+            .or_else(|| self.config().get(ConfigKey::ClientKey))
+            .ok_or_else(|| KSMRError::Config("cannot locate one-time token".into()))?;
+        let (_, token) = Self::parse_token(&token)?;
+
+        let token_bytes = url_safe_str_to_bytes(&token)?;
+        let mut mac = Hmac::<Sha512>::new_from_slice(&token_bytes)
+            .map_err(|e| KSMRError::Crypto(e.to_string()))?;
+        mac.update(b"KEEPER_SECRETS_MANAGER_CLIENT_ID");
+        let client_id = bytes_to_base64(&mac.finalize().into_bytes());
+        self.config().set(ConfigKey::ClientId, client_id);
+
+        if self.config().get(ConfigKey::PrivateKey).is_none() {
+            let private_key = crypto::generate_private_key_ecc_with(self.rng())?;
+            self.config()
+                .set(ConfigKey::PrivateKey, bytes_to_base64(&private_key.to_bytes()));
+        }
+
+        if self.config().get(ConfigKey::ServerPublicKeyId).is_none() {
+            self.config()
+                .set(ConfigKey::ServerPublicKeyId, DEFAULT_KEY_ID.to_string());
+        }
+
+        Ok(())
+    }
+
+    fn private_key(&self) -> Result<SecretKey, KSMRError> {
+        let stored = self
+            .config()
+            .get(ConfigKey::PrivateKey)
+            .ok_or_else(|| KSMRError::Config("client is not bound: missing private key".into()))?;
+        let bytes = crate::utils::base64_to_bytes(&stored)?;
+        SecretKey::from_slice(&bytes).map_err(|e| KSMRError::Crypto(e.to_string()))
+    }
+
+    /// Returns the raw, decrypted application key held in config after binding.
+    ///
+    /// # Security
+    ///
+    /// The application key decrypts every record and folder key for this
+    /// client. Exporting it defeats the SDK's usual "keys never leave the
+    /// process" posture, so this is gated behind the `unsafe-export-keys`
+    /// feature and should only be used for bridging to other Keeper tooling
+    /// that needs the same key material. Treat the returned bytes as highly
+    /// sensitive: avoid logging them, and let the `Zeroizing` wrapper clear
+    /// them from memory once dropped.
+    #[cfg(feature = "unsafe-export-keys")]
+    pub fn app_key_bytes(&self) -> Result<zeroize::Zeroizing<Vec<u8>>, KSMRError> {
+        let stored = self
+            .config()
+            .get(ConfigKey::AppKey)
+            .ok_or_else(|| KSMRError::Config("app key is not available: client is not bound".into()))?;
+        Ok(zeroize::Zeroizing::new(base64_to_bytes(&stored)?))
+    }
+
+    fn hostname(&self) -> Result<String, KSMRError> {
+        self.options
+            .hostname
+            .clone()
+            .or_else(|| self.config().get(ConfigKey::Hostname))
+            .ok_or_else(|| KSMRError::Config("hostname is not set".into()))
+    }
+
+    /// Compares this machine's clock against the configured Keeper server's
+    /// clock and returns the skew. Request signing and TOTP codes both assume
+    /// an accurate local clock; skew beyond a few minutes is a common cause of
+    /// otherwise-opaque signature/TOTP failures, especially in containers
+    /// that don't run an NTP daemon.
+    pub fn check_clock_skew(&self) -> Result<Duration, KSMRError> {
+        let server_date = self.fetch_server_date()?;
+        let server_time = parse_http_date(&server_date)?;
+        let local_time = SystemTime::now();
+        let skew = server_time
+            .duration_since(local_time)
+            .or_else(|_| local_time.duration_since(server_time))
+            .map_err(|e| KSMRError::Other(e.to_string()))?;
+        Ok(skew)
+    }
+
+    /// Returns the server's current time as an RFC 7231 HTTP-date, read from
+    /// the `Date` header of a plain, unauthenticated request (no transmission
+    /// key exchange needed - we just want the gateway's clock).
+    fn fetch_server_date(&self) -> Result<String, KSMRError> {
+        if let Some(override_fn) = &self.options.server_date_override {
+            return override_fn();
+        }
+
+        let url = format!("https://{}/api/rest/sm/v1/", self.hostname()?);
+        let client = reqwest::blocking::Client::builder()
+            .danger_accept_invalid_certs(!self.effective_verify_ssl_certs())
+            .build()
+            .map_err(|e| KSMRError::Network(e.to_string()))?;
+        let response = client.head(&url).send().map_err(|e| KSMRError::Network(e.to_string()))?;
+        response
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| KSMRError::Network("server response did not include a Date header".into()))
+    }
+
+    /// Generates a fresh transmission key and wraps it for the configured server public key.
+    fn generate_transmission_key(&self) -> Result<(Vec<u8>, Vec<u8>), KSMRError> {
+        let key_id = self
+            .config()
+            .get(ConfigKey::ServerPublicKeyId)
+            .unwrap_or_else(|| DEFAULT_KEY_ID.to_string());
+        let server_public_key = keeper_public_key(&key_id)
+            .ok_or_else(|| KSMRError::Config(format!("no server public key for id {key_id}")))?;
+        let transmission_key = generate_encryption_key_bytes_with(self.rng());
+        let encrypted_key =
+            crypto::public_encrypt_with(self.rng(), &transmission_key, &server_public_key)?;
+        Ok((transmission_key, encrypted_key))
+    }
+
+    fn encrypt_and_sign_payload<T: serde::Serialize>(
+        &self,
+        transmission_key: &[u8],
+        encrypted_transmission_key: &[u8],
+        payload: &T,
+    ) -> Result<(Vec<u8>, Vec<u8>), KSMRError> {
+        let payload_json = serde_json::to_vec(payload).map_err(|e| KSMRError::Serialization(e.to_string()))?;
+        let encrypted_payload =
+            crypto::encrypt_aes_gcm_with(self.rng(), transmission_key, &payload_json)?;
+        let mut signature_base = encrypted_transmission_key.to_vec();
+        signature_base.extend(&encrypted_payload);
+        let private_key = self.private_key()?;
+        let signature = self.signer().sign(&signature_base, &private_key);
+        // Self-verification is pure overhead on the hot path in release builds: the
+        // server is the one that actually needs to verify this signature. Keep it as
+        // a debug-only sanity check instead of doubling the ECDSA work per request.
+        debug_assert!(
+            self.signer().verify(&signature_base, &signature, &private_key),
+            "just-generated signature failed to verify against our own private key"
+        );
+        Ok((encrypted_payload, signature))
+    }
+
+    /// Sends an encrypted, signed request to `path` and returns the *decrypted* response
+    /// body. Set `custom_post_function` on [`ClientOptions`] to intercept the transport
+    /// (e.g. in tests) while still going through the same encrypt/sign/decrypt pipeline.
+    pub fn post_query(&self, path: &str, payload_json: &impl serde::Serialize) -> Result<Vec<u8>, KSMRError> {
+        self.post_query_reporting_source(path, payload_json).0
+    }
+
+    /// Like [`SecretsManager::post_query`], but also reports whether the
+    /// result came from a live call or [`ClientOptions::enable_disaster_recovery_cache`]'s
+    /// cache, for callers like [`SecretsManager::fetch_and_decrypt_secrets`]
+    /// that need to set [`crate::dto::ResponseSource`] on their own response.
+    fn post_query_reporting_source(
+        &self,
+        path: &str,
+        payload_json: &impl serde::Serialize,
+    ) -> (Result<Vec<u8>, KSMRError>, ResponseSource) {
+        let started_at = std::time::Instant::now();
+        let mut status = None;
+        let mut cache_hit = false;
+        let result = self.post_query_inner(path, payload_json, &mut status, &mut cache_hit);
+        if let Some(callback) = &self.options.metrics_callback {
+            callback(RequestMetrics { path: path.to_string(), duration: started_at.elapsed(), status, retried: false, cache_hit });
+        }
+        if let Some(callback) = &self.options.audit_callback {
+            callback(AuditEvent {
+                path: path.to_string(),
+                timestamp_millis: crate::utils::now_milliseconds() as u64,
+                success: result.is_ok(),
+                status,
+            });
+        }
+        let source = if cache_hit { ResponseSource::Cache } else { ResponseSource::Live };
+        (result, source)
+    }
+
+    /// Whether `path` is eligible for [`ClientOptions::enable_disaster_recovery_cache`]:
+    /// just `get_secret`, the one call both [`SecretsManager::get_secrets`] and
+    /// [`SecretsManager::get_folders`] go through.
+    fn is_cacheable(path: &str) -> bool {
+        path == "get_secret"
+    }
+
+    /// Whether a 200 response from `path` is expected to carry a decryptable
+    /// body. Most endpoints (`create_secret`, `update_secret`,
+    /// `create_folder`, `update_folder`, ...) are fire-and-forget: the caller
+    /// only cares whether the call succeeded, and the gateway's 200 for them
+    /// has always been an empty body. `get_secret` and `add_file` are
+    /// different - their callers decode the response - so an empty body from
+    /// one of those is a real backend anomaly, not a "successfully empty"
+    /// result, and should surface as an error instead of silently becoming
+    /// an empty `Vec<u8>` that callers like [`SecretsManager::get_secrets`]
+    /// would otherwise read as "zero records" or "zero folders".
+    fn expects_response_body(path: &str) -> bool {
+        matches!(path, "get_secret" | "add_file")
+    }
+
+    /// Builds the error for a non-200 `post_query` response to `path`. The
+    /// gateway's error responses are plain (unencrypted) JSON carrying an
+    /// `error` (or, on some older endpoints, `result_code`) code; when that
+    /// code is `invalid_client_version` - the backend no longer recognizes
+    /// the `clientVersion` this SDK sends with every request - this returns
+    /// [`KSMRError::ClientVersion`] with the server's `additional_info`
+    /// instead of a generic network error, since upgrading this SDK is
+    /// almost always the fix and a caller shouldn't have to decode a raw
+    /// HTTP status to discover that. Any other error body, or a body that
+    /// isn't JSON at all, falls back to [`KSMRError::Network`].
+    fn parse_http_error(path: &str, response: &KsmHttpResponse) -> KSMRError {
+        if let Ok(body) = serde_json::from_slice::<serde_json::Value>(&response.data) {
+            let error_code =
+                body.get("error").or_else(|| body.get("result_code")).and_then(serde_json::Value::as_str);
+            if error_code == Some("invalid_client_version") {
+                let additional_info = body
+                    .get("additional_info")
+                    .and_then(serde_json::Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                return KSMRError::ClientVersion(additional_info);
+            }
+        }
+        KSMRError::Network(format!("request to {path} failed with status {}", response.status_code))
+    }
+
+    /// Filters [`ClientOptions::extra_headers`] down to the ones the default
+    /// `post_function` is actually allowed to send: everything except
+    /// `Content-Type`, which this protocol always sets itself to
+    /// `application/octet-stream` (the wire body is the encrypted
+    /// transmission key, payload and signature concatenated, never JSON or
+    /// form-encoded). Pulled out as its own function so the filtering can be
+    /// exercised without performing a real HTTP request.
+    fn filtered_extra_headers(extra_headers: &std::collections::HashMap<String, String>) -> Vec<(&str, &str)> {
+        extra_headers
+            .iter()
+            .filter(|(name, _)| !name.eq_ignore_ascii_case("Content-Type"))
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+
+    fn post_query_inner(
+        &self,
+        path: &str,
+        payload_json: &impl serde::Serialize,
+        status_out: &mut Option<u16>,
+        cache_hit_out: &mut bool,
+    ) -> Result<Vec<u8>, KSMRError> {
+        let (transmission_key, encrypted_transmission_key) = self.generate_transmission_key()?;
+        let (encrypted_payload, signature) =
+            self.encrypt_and_sign_payload(&transmission_key, &encrypted_transmission_key, payload_json)?;
+
+        let mut body = encrypted_transmission_key;
+        body.extend(&encrypted_payload);
+        body.extend(&signature);
+
+        let url = format!("https://{}/api/rest/sm/v1/{}", self.hostname()?, path);
+
+        let is_binding = self.config().get(ConfigKey::AppKey).is_none();
+        let bind_timeout = is_binding.then_some(self.options.bind_timeout).flatten();
+
+        let sent = if let Some(custom) = &self.options.custom_post_function {
+            custom(&url, &body, self.effective_verify_ssl_certs())
+        } else {
+            (|| {
+                let mut builder = reqwest::blocking::Client::builder()
+                    .danger_accept_invalid_certs(!self.effective_verify_ssl_certs());
+                if let Some(timeout) = bind_timeout {
+                    builder = builder.timeout(timeout);
+                }
+                let client = builder.build().map_err(|e| KSMRError::Network(e.to_string()))?;
+                let mut request = client.post(&url).header("Content-Type", "application/octet-stream");
+                for (name, value) in Self::filtered_extra_headers(&self.options.extra_headers) {
+                    request = request.header(name, value);
+                }
+                let http_response = request.body(body).send().map_err(|e| {
+                    if let Some(timeout) = bind_timeout {
+                        if e.is_timeout() {
+                            return KSMRError::BindTimeout(timeout);
+                        }
+                    }
+                    KSMRError::Network(e.to_string())
+                })?;
+                let status_code = http_response.status().as_u16();
+                let data = http_response.bytes().map_err(|e| KSMRError::Network(e.to_string()))?.to_vec();
+                Ok(KsmHttpResponse { status_code, data })
+            })()
+        };
+
+        let response = match sent {
+            Ok(response) => response,
+            Err(e) => {
+                if self.options.enable_disaster_recovery_cache && Self::is_cacheable(path) {
+                    if let Some(cached) = self.dr_cache.lock().unwrap().clone() {
+                        self.log(LogLevel::Warn, format!("POST {path} failed ({e}); serving cached response"));
+                        *cache_hit_out = true;
+                        return Ok(cached);
+                    }
+                }
+                return Err(e);
+            }
+        };
+
+        *status_out = Some(response.status_code);
+        self.log(LogLevel::Debug, format!("POST {path} -> {}", response.status_code));
+
+        if response.status_code != 200 {
+            return Err(Self::parse_http_error(path, &response));
+        }
+
+        if response.data.is_empty() {
+            if Self::expects_response_body(path) {
+                return Err(KSMRError::Network(format!(
+                    "request to {path} succeeded with status 200 but returned an empty body"
+                )));
+            }
+            return Ok(response.data);
+        }
+        let decrypted = crypto::decrypt_aes_gcm(&transmission_key, &response.data)?;
+        if self.options.enable_disaster_recovery_cache && Self::is_cacheable(path) {
+            *self.dr_cache.lock().unwrap() = Some(decrypted.clone());
+        }
+        Ok(decrypted)
+    }
+
+    /// Builds the `GetPayload` for fetching (and, on first bind, decrypting) secrets.
+    /// Borrows `records_filter` rather than taking ownership, so callers that may need
+    /// to issue the request again (e.g. [`get_secrets`](Self::get_secrets) retrying once
+    /// after the initial bind) don't have to clone it up front.
+    pub fn prepare_get_payload(&self, records_filter: Option<&[String]>) -> Result<GetPayload, KSMRError> {
+        let public_key = if self.config().get(ConfigKey::AppKey).is_none() {
+            Some(bytes_to_base64(&public_key_ecc(&self.private_key()?)))
+        } else {
+            None
+        };
+        Ok(GetPayload {
+            client_version: KEEPER_SECRETS_MANAGER_SDK_CLIENT_ID.to_string(),
+            client_id: self
+                .config()
+                .get(ConfigKey::ClientId)
+                .ok_or_else(|| KSMRError::Config("client is not bound: missing client id".into()))?,
+            public_key,
+            requested_records: records_filter.map(<[String]>::to_vec),
+        })
+    }
+
+    /// Builds the `CreatePayload` for a new record under `folder_key`, returning the
+    /// payload plus the record's plaintext encryption key (needed to decrypt it locally
+    /// without a round trip). Pure and RNG-seamed so generated payloads can be snapshot-tested.
+    pub fn prepare_create_payload(
+        &self,
+        folder_uid: &str,
+        folder_key: &[u8],
+        record: &RecordCreate,
+    ) -> Result<(CreatePayload, Vec<u8>), KSMRError> {
+        if folder_key.is_empty() {
+            return Err(KSMRError::FolderNotFound(folder_uid.to_string()));
+        }
+        record.validate()?;
+        let record_uid_bytes = generate_random_bytes_with(self.rng(), 16);
+        let record_uid = bytes_to_url_safe_str(&record_uid_bytes);
+        let record_key = generate_encryption_key_bytes_with(self.rng());
+
+        let record_json = serde_json::to_vec(record).map_err(|e| KSMRError::Serialization(e.to_string()))?;
+        let encrypted_data = crypto::encrypt_aes_gcm_with(self.rng(), &record_key, &record_json)?;
+        let encrypted_record_key = crypto::encrypt_aes_gcm_with(self.rng(), folder_key, &record_key)?;
+
+        let payload = CreatePayload {
+            client_version: KEEPER_SECRETS_MANAGER_SDK_CLIENT_ID.to_string(),
+            client_id: self
+                .config()
+                .get(ConfigKey::ClientId)
+                .ok_or_else(|| KSMRError::Config("client is not bound: missing client id".into()))?,
+            record_uid,
+            record_key: bytes_to_base64(&encrypted_record_key),
+            folder_uid: folder_uid.to_string(),
+            folder_key: bytes_to_base64(folder_key),
+            data: bytes_to_base64(&encrypted_data),
+        };
+        Ok((payload, record_key))
+    }
+
+    /// Creates a record under `folder_uid`, whose decrypted key is `folder_key`
+    /// (see [`SecretsManager::get_folder_key`]). Returns the new record's uid.
+    pub fn create_secret(
+        &self,
+        folder_uid: &str,
+        folder_key: &[u8],
+        record: &RecordCreate,
+    ) -> Result<String, KSMRError> {
+        let (payload, _record_key) = self.prepare_create_payload(folder_uid, folder_key, record)?;
+        let record_uid = payload.record_uid.clone();
+        self.post_query("create_secret", &payload)?;
+        Ok(record_uid)
+    }
+
+    /// Creates a record the same way [`SecretsManager::create_secret`] does,
+    /// then immediately fetches it back so the caller gets a usable
+    /// [`Record`] - decryptable key, revision, `is_editable` and all -
+    /// instead of just the new uid and an awkward follow-up
+    /// [`SecretsManager::get_secrets`] call before it can update or delete
+    /// what it just created.
+    pub fn create_secret_and_fetch(
+        &self,
+        folder_uid: &str,
+        folder_key: &[u8],
+        record: &RecordCreate,
+    ) -> Result<Record, KSMRError> {
+        let record_uid = self.create_secret(folder_uid, folder_key, record)?;
+        self.get_secrets(Some(vec![record_uid.clone()]))?
+            .into_iter()
+            .next()
+            .ok_or(KSMRError::RecordNotFound(record_uid))
+    }
+
+    /// Re-encrypts an already-decrypted `record` under a freshly generated
+    /// record key and wraps that key for `dest_owner_public_key` instead of a
+    /// folder key, producing a [`CreatePayload`] a vault-to-vault migration
+    /// tool can hand to a *different* application's `create_secret` call.
+    /// This is the same owner-public-key wrap this crate's file-upload path
+    /// already uses for file keys, reused here because a cross-app transfer
+    /// has no folder key in common between the source and destination apps
+    /// to wrap the record key with instead.
+    ///
+    /// The encrypted `data` carries only [`ReencryptedRecordData`] - `type`,
+    /// `title`, `fields`, `custom`, `notes` - not the source `record`'s own
+    /// `uid`/`folder_uid`/`revision`. Those describe the *source* vault's
+    /// bookkeeping, not the secret itself, and have no business ending up
+    /// inside the destination application's encrypted data.
+    ///
+    /// This crate has no dedicated record-transfer endpoint, and the
+    /// gateway's `create_secret` call otherwise expects `folder_key` to be a
+    /// real folder's symmetric key; there is no such key shared with an
+    /// application this client has never bound to, so `folder_key` is left
+    /// an empty string here rather than filled with something misleading.
+    /// `folder_uid` is carried over unchanged from `record.folder_uid`
+    /// (empty if the record isn't filed under one), since a migration tool
+    /// is expected to recreate the same folder structure on the destination
+    /// side before replaying these payloads there. The destination
+    /// application is responsible for unwrapping `record_key` with its own
+    /// private key - the same way [`crypto::private_decrypt`] unwraps a
+    /// newly bound client's `encryptedAppKey` - rather than a folder key,
+    /// before filing the record away.
+    pub fn reencrypt_record_for(
+        &self,
+        record: &Record,
+        dest_owner_public_key: &[u8],
+    ) -> Result<CreatePayload, KSMRError> {
+        let record_uid = bytes_to_url_safe_str(&generate_random_bytes_with(self.rng(), 16));
+        let record_key = generate_encryption_key_bytes_with(self.rng());
+
+        let data = ReencryptedRecordData {
+            record_type: record.record_type.clone(),
+            title: record.title.clone(),
+            fields: record.fields.clone(),
+            custom: record.custom.clone(),
+            notes: record.notes.clone(),
+        };
+        let record_json = serde_json::to_vec(&data).map_err(|e| KSMRError::Serialization(e.to_string()))?;
+        let encrypted_data = crypto::encrypt_aes_gcm_with(self.rng(), &record_key, &record_json)?;
+        let encrypted_record_key =
+            crypto::public_encrypt_with(self.rng(), &record_key, dest_owner_public_key)?;
+
+        Ok(CreatePayload {
+            client_version: KEEPER_SECRETS_MANAGER_SDK_CLIENT_ID.to_string(),
+            client_id: self
+                .config()
+                .get(ConfigKey::ClientId)
+                .ok_or_else(|| KSMRError::Config("client is not bound: missing client id".into()))?,
+            record_uid,
+            record_key: bytes_to_base64(&encrypted_record_key),
+            folder_uid: record.folder_uid.clone().unwrap_or_default(),
+            folder_key: String::new(),
+            data: bytes_to_base64(&encrypted_data),
+        })
+    }
+
+    /// Creates a secure note record (`type: "note"`, a single `note` field
+    /// holding `body`) under `folder_uid`, without requiring the caller to
+    /// assemble a [`RecordCreate`] field list by hand. Returns the new
+    /// record's uid. See [`Record::note_body`] for reading it back.
+    pub fn create_note(
+        &self,
+        folder_uid: &str,
+        folder_key: &[u8],
+        title: &str,
+        body: &str,
+    ) -> Result<String, KSMRError> {
+        let mut record = RecordCreate::new("note", title);
+        record.fields.push(RecordField::new("note", vec![serde_json::Value::String(body.to_string())]));
+        self.create_secret(folder_uid, folder_key, &record)
+    }
+
+    /// Creates a record and attaches `files` to it in one call, so a
+    /// provisioning step doesn't leave a record with no attachments if it
+    /// crashes between `create_secret` and `upload_file`. Files already
+    /// uploaded before a failure are **not** rolled back - the gateway has no
+    /// delete endpoint for this SDK to call - but the returned error names
+    /// the record uid and how many files made it through, so the caller can
+    /// retry the remaining uploads against the now-existing record instead of
+    /// re-creating it.
+    pub fn create_secret_with_files(
+        &self,
+        folder_uid: &str,
+        folder_key: &[u8],
+        record: &RecordCreate,
+        files: Vec<KeeperFileUpload>,
+    ) -> Result<String, KSMRError> {
+        let (payload, record_key) = self.prepare_create_payload(folder_uid, folder_key, record)?;
+        let record_uid = payload.record_uid.clone();
+        self.post_query("create_secret", &payload)?;
+
+        let mut owner_record = Record {
+            uid: record_uid.clone(),
+            title: record.title.clone(),
+            record_type: record.record_type.clone(),
+            fields: record.fields.clone(),
+            ..Default::default()
+        };
+        owner_record.record_key_bytes = record_key;
+
+        let total = files.len();
+        for (uploaded, file) in files.into_iter().enumerate() {
+            let file_name = file.name.clone();
+            self.upload_file(&mut owner_record, &file).map_err(|e| {
+                KSMRError::Other(format!(
+                    "record {record_uid} was created but uploading file '{file_name}' failed after \
+                     {uploaded}/{total} file(s) succeeded: {e}"
+                ))
+            })?;
+        }
+
+        Ok(record_uid)
+    }
+
+    /// Builds the `FileUploadPayload` for attaching `file` to `owner_record`,
+    /// adding its uid to `owner_record`'s `fileRef` field (creating one if it
+    /// doesn't already have one) and re-encrypting the owner record under its
+    /// own key so the new `fileRef` is persisted alongside the file itself.
+    /// Returns the payload plus the file's own AES-GCM-encrypted bytes, ready
+    /// to be uploaded to the URL an `add_file` call returns.
+    fn prepare_file_upload_payload(
+        &self,
+        owner_record: &mut Record,
+        file: &KeeperFileUpload,
+    ) -> Result<(FileUploadPayload, Vec<u8>), KSMRError> {
+        if owner_record.record_key_bytes.is_empty() {
+            return Err(KSMRError::RecordNotFound(owner_record.uid.clone()));
+        }
+        let owner_public_key_b64 = self.config().get(ConfigKey::OwnerPublicKey).ok_or_else(|| {
+            KSMRError::Config(
+                "cannot upload file: owner public key is missing (app may need re-binding)".into(),
+            )
+        })?;
+        let owner_public_key = base64_to_bytes(&owner_public_key_b64)?;
+
+        let last_modified = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let file_meta = FileRecordMeta {
+            name: file.name.clone(),
+            size: file.data.len(),
+            title: file.title.clone(),
+            last_modified,
+            mime_type: file.mime_type.clone(),
+        };
+        let file_meta_json =
+            serde_json::to_vec(&file_meta).map_err(|e| KSMRError::Serialization(e.to_string()))?;
+
+        let file_record_key = generate_encryption_key_bytes_with(self.rng());
+        let file_record_uid = bytes_to_url_safe_str(&generate_random_bytes_with(self.rng(), 16));
+
+        let encrypted_file_record = crypto::encrypt_aes_gcm_with(self.rng(), &file_record_key, &file_meta_json)?;
+        let encrypted_file_record_key =
+            crypto::public_encrypt_with(self.rng(), &file_record_key, &owner_public_key)?;
+        let encrypted_link_key =
+            crypto::encrypt_aes_gcm_with(self.rng(), &owner_record.record_key_bytes, &file_record_key)?;
+        let encrypted_file_data = crypto::encrypt_aes_gcm_with(self.rng(), &file_record_key, &file.data)?;
+
+        match owner_record.fields.iter_mut().find(|f| f.field_type == "fileRef") {
+            Some(field) => field.value.push(serde_json::Value::String(file_record_uid.clone())),
+            None => owner_record
+                .fields
+                .push(RecordField::new("fileRef", vec![serde_json::Value::String(file_record_uid.clone())])),
+        }
+
+        let owner_record_json =
+            serde_json::to_vec(&*owner_record).map_err(|e| KSMRError::Serialization(e.to_string()))?;
+        let encrypted_owner_record =
+            crypto::encrypt_aes_gcm_with(self.rng(), &owner_record.record_key_bytes, &owner_record_json)?;
+
+        let payload = FileUploadPayload {
+            client_version: KEEPER_SECRETS_MANAGER_SDK_CLIENT_ID.to_string(),
+            client_id: self
+                .config()
+                .get(ConfigKey::ClientId)
+                .ok_or_else(|| KSMRError::Config("client is not bound: missing client id".into()))?,
+            file_record_uid,
+            file_record_key: bytes_to_base64(&encrypted_file_record_key),
+            file_record_data: bytes_to_url_safe_str(&encrypted_file_record),
+            owner_record_uid: owner_record.uid.clone(),
+            owner_record_data: bytes_to_url_safe_str(&encrypted_owner_record),
+            link_key: bytes_to_base64(&encrypted_link_key),
+            file_size: encrypted_file_data.len(),
+        };
+
+        Ok((payload, encrypted_file_data))
+    }
+
+    /// Uploads `file` and attaches it to `owner_record` (which must already
+    /// have `record_key_bytes` populated, e.g. from a prior `get_secrets` or
+    /// `create_secret` call). Returns the new file record's uid.
+    pub fn upload_file(&self, owner_record: &mut Record, file: &KeeperFileUpload) -> Result<String, KSMRError> {
+        let (payload, encrypted_file_data) = self.prepare_file_upload_payload(owner_record, file)?;
+        let file_record_uid = payload.file_record_uid.clone();
+
+        let response = self.post_query("add_file", &payload)?;
+        let response_str = bytes_to_string(&response)?;
+        let add_file_response: AddFileResponse =
+            serde_json::from_str(&response_str).map_err(|e| KSMRError::Serialization(e.to_string()))?;
+        let parameters: std::collections::HashMap<String, String> =
+            serde_json::from_str(&add_file_response.parameters)
+                .map_err(|e| KSMRError::Serialization(e.to_string()))?;
+
+        self.upload_file_data(&add_file_response.url, &parameters, &encrypted_file_data)?;
+
+        Ok(file_record_uid)
+    }
+
+    /// Performs the raw multipart upload of `data` to the presigned `url`
+    /// returned by `add_file`, submitted with the server-provided `parameters`
+    /// as additional form fields (S3 policy signature, key, etc.).
+    fn upload_file_data(
+        &self,
+        url: &str,
+        parameters: &std::collections::HashMap<String, String>,
+        data: &[u8],
+    ) -> Result<(), KSMRError> {
+        let attempts = self.options.upload_retries + 1;
+        for attempt in 1..=attempts {
+            match self.upload_file_data_once(url, parameters, data) {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < attempts => {
+                    self.log(LogLevel::Warn, format!("file upload attempt {attempt} failed ({e}); retrying"));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!("upload_file_data_once always returns before the loop runs out of attempts")
+    }
+
+    /// A single attempt at the raw multipart upload, with no retrying - see
+    /// [`SecretsManager::upload_file_data`] for the retry loop around this.
+    fn upload_file_data_once(
+        &self,
+        url: &str,
+        parameters: &std::collections::HashMap<String, String>,
+        data: &[u8],
+    ) -> Result<(), KSMRError> {
+        if let Some(override_fn) = &self.options.file_upload_override {
+            return override_fn(url, parameters, data);
+        }
+
+        let mut builder = reqwest::blocking::Client::builder()
+            .danger_accept_invalid_certs(!self.effective_verify_ssl_certs());
+        if let Some(timeout) = self.options.upload_timeout {
+            builder = builder.timeout(timeout);
+        }
+        let client = builder.build().map_err(|e| KSMRError::Network(e.to_string()))?;
+        let mut form = reqwest::blocking::multipart::Form::new();
+        for (key, value) in parameters {
+            form = form.text(key.clone(), value.clone());
+        }
+        form = form.part("file", reqwest::blocking::multipart::Part::bytes(data.to_vec()));
+
+        let response = client
+            .post(url)
+            .multipart(form)
+            .send()
+            .map_err(|e| KSMRError::Network(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(KSMRError::Network(format!("file upload failed with status {}", response.status())));
+        }
+        Ok(())
+    }
+
+    /// Verifies that a downloaded, decrypted attachment matches the size its
+    /// file record declared, catching a truncated or swapped download that
+    /// the AES-GCM tag alone wouldn't (the tag only proves the ciphertext
+    /// wasn't altered, not that it's the file the record claims it is).
+    /// Returns [`KSMRError::Crypto`] on a mismatch.
+    ///
+    /// The `add_file`/upload wire protocol this SDK implements doesn't carry
+    /// a separate content hash for attachments (only `size`), so there is no
+    /// hash to check here; if the protocol ever adds one, it belongs in this
+    /// function alongside the size check.
+    pub fn verify_file_integrity(meta: &FileRecordMeta, decrypted: &[u8]) -> Result<(), KSMRError> {
+        if decrypted.len() != meta.size {
+            return Err(KSMRError::Crypto(format!(
+                "downloaded file '{}' is {} bytes, but its file record declares {} bytes",
+                meta.name,
+                decrypted.len(),
+                meta.size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Performs the raw GET of the still-encrypted file bytes from the
+    /// presigned `url` a `request_download` call returned, the download-side
+    /// counterpart to [`SecretsManager::upload_file_data`].
+    fn download_file_data(&self, url: &str) -> Result<Vec<u8>, KSMRError> {
+        let attempts = self.options.download_retries + 1;
+        let mut received: Vec<u8> = Vec::new();
+        let mut resuming = false;
+        for attempt in 1..=attempts {
+            match self.download_file_data_once(url, &mut received, &mut resuming) {
+                Ok(()) => return Ok(received),
+                Err(e) if attempt < attempts => {
+                    self.log(
+                        LogLevel::Warn,
+                        format!(
+                            "file download attempt {attempt} failed ({e}); retrying{}",
+                            if resuming { " with a range request" } else { "" }
+                        ),
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!("download_file_data_once always returns before the loop runs out of attempts")
+    }
+
+    /// Performs one download attempt, appending any newly-received bytes to
+    /// `received` and updating `resuming` to whether the next attempt (if
+    /// any) should continue with a `Range` request instead of starting
+    /// over. [`ClientOptions::file_download_override`], when set, has no
+    /// concept of a partial response - each attempt asks it for the whole
+    /// file again, so `received` is simply overwritten rather than resumed.
+    fn download_file_data_once(
+        &self,
+        url: &str,
+        received: &mut Vec<u8>,
+        resuming: &mut bool,
+    ) -> Result<(), KSMRError> {
+        if let Some(override_fn) = &self.options.file_download_override {
+            *received = override_fn(url)?;
+            return Ok(());
+        }
+
+        let client = reqwest::blocking::Client::builder()
+            .danger_accept_invalid_certs(!self.effective_verify_ssl_certs())
+            .build()
+            .map_err(|e| KSMRError::Network(e.to_string()))?;
+        let mut request = client.get(url);
+        if *resuming {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", received.len()));
+        }
+        let mut response = request.send().map_err(|e| KSMRError::Network(e.to_string()))?;
+        let status = response.status();
+        if *resuming && status.is_success() && status.as_u16() != 206 {
+            // The storage URL didn't honor the Range request - what's about to
+            // be read is the whole file again, not a continuation. A transient
+            // error status (e.g. 500/503) isn't this: the server hasn't said
+            // anything about range support, so `received`/`resuming` are left
+            // alone and the next attempt can still retry the range request.
+            received.clear();
+            *resuming = false;
+        } else if status.as_u16() == 206 {
+            *resuming = true;
+        }
+        if !status.is_success() {
+            return Err(KSMRError::Network(format!("file download failed with status {status}")));
+        }
+        let mut chunk = [0u8; 65536];
+        loop {
+            match std::io::Read::read(&mut response, &mut chunk) {
+                Ok(0) => return Ok(()),
+                Ok(n) => received.extend_from_slice(&chunk[..n]),
+                Err(e) => {
+                    *resuming = !received.is_empty();
+                    return Err(KSMRError::Network(e.to_string()));
+                }
+            }
+        }
+    }
+
+    /// Streams the decrypted bytes of `owner_record`'s attachment `file_uid`
+    /// (a uid already present in its `fileRef` field, e.g. from
+    /// [`SecretsManager::upload_file`]) to `writer`, returning the number of
+    /// plaintext bytes written.
+    ///
+    /// AES-256-GCM, the cipher this SDK's file attachments are encrypted
+    /// with, appends its authentication tag at the *end* of the ciphertext,
+    /// so the complete encrypted download has to be in hand before a single
+    /// byte of it can be trusted - there is no way to verify-while-streaming
+    /// with this wire format. What this method actually avoids is a
+    /// *second* full in-memory copy: the encrypted download is buffered
+    /// once and decrypted once, and the resulting plaintext is written
+    /// straight to `writer` rather than also being collected into an owned
+    /// `Vec<u8>` for the caller to copy out themselves afterward.
+    pub fn download_file_to_writer(
+        &self,
+        owner_record: &Record,
+        file_uid: &str,
+        writer: &mut impl std::io::Write,
+    ) -> Result<u64, KSMRError> {
+        let is_attached = owner_record
+            .field_by_type("fileRef")
+            .is_some_and(|f| f.value.iter().any(|v| v.as_str() == Some(file_uid)));
+        if !is_attached {
+            return Err(KSMRError::RecordNotFound(format!(
+                "'{file_uid}' is not listed in record {}'s fileRef field",
+                owner_record.uid
+            )));
+        }
+
+        let file_record = self
+            .get_secrets(Some(vec![file_uid.to_string()]))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| KSMRError::RecordNotFound(file_uid.to_string()))?;
+
+        let payload = RequestDownloadPayload {
+            client_version: KEEPER_SECRETS_MANAGER_SDK_CLIENT_ID.to_string(),
+            client_id: self
+                .config()
+                .get(ConfigKey::ClientId)
+                .ok_or_else(|| KSMRError::Config("client is not bound: missing client id".into()))?,
+            file_record_uid: file_uid.to_string(),
+        };
+        let response = self.post_query("request_download", &payload)?;
+        let response_str = bytes_to_string(&response)?;
+        let download_response: RequestDownloadResponse =
+            serde_json::from_str(&response_str).map_err(|e| KSMRError::Serialization(e.to_string()))?;
+
+        let encrypted_data = self.download_file_data(&download_response.url)?;
+        let plaintext = crypto::decrypt_aes_gcm(&file_record.record_key_bytes, &encrypted_data)?;
+
+        writer
+            .write_all(&plaintext)
+            .map_err(|e| KSMRError::Other(format!("failed to write downloaded file to writer: {e}")))?;
+        Ok(plaintext.len() as u64)
+    }
+
+    /// Fetches the name, size, title, mime type and last-modified time of
+    /// every file attached to `owner_record` via its `fileRef` field,
+    /// without downloading or decrypting any attachment's actual content
+    /// from storage. This only decrypts each file record's own small
+    /// metadata blob (the same bytes [`SecretsManager::upload_file`] wrote
+    /// it from) - no `request_download` call, no fetch from the presigned
+    /// storage URL. Reach for [`SecretsManager::download_file_to_writer`]
+    /// once a caller has actually picked an attachment from the list this
+    /// returns.
+    pub fn list_attachments(&self, owner_record: &Record) -> Result<Vec<AttachmentInfo>, KSMRError> {
+        let file_uids: Vec<String> = match owner_record.field_by_type("fileRef") {
+            Some(field) => field.value.iter().filter_map(|v| v.as_str().map(str::to_string)).collect(),
+            None => return Ok(Vec::new()),
+        };
+        if file_uids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (mut wire, mut secret_key, just_bound, _source) = self.fetch_wire_response(Some(&file_uids))?;
+        if just_bound {
+            // The first response after binding only carries the wrapped app key, no
+            // records - re-fetch now that it's been unwrapped and stored. Mirrors the
+            // retry `SecretsManager::get_secrets` does for the same reason.
+            let retry = self.fetch_wire_response(Some(&file_uids))?;
+            wire = retry.0;
+            secret_key = retry.1;
+        }
+
+        let mut warnings = Vec::new();
+        let mut attachments = Vec::with_capacity(wire.records.len());
+        for r in &wire.records {
+            let (_, plaintext) = self.decrypt_wire_record_plaintext(r, &secret_key, None, &mut warnings)?;
+            let meta: FileRecordMeta = crate::utils::json_to_dict(&bytes_to_string(&plaintext)?)?;
+            attachments.push(AttachmentInfo {
+                uid: r.record_uid.clone(),
+                name: meta.name,
+                size: meta.size,
+                title: meta.title,
+                last_modified: meta.last_modified,
+                mime_type: meta.mime_type,
+            });
+        }
+        Ok(attachments)
+    }
+
+    /// Builds the `UpdatePayload` for `record`, re-encrypting its (already
+    /// serialized) contents under its own `record_key_bytes`.
+    fn prepare_update_payload(
+        &self,
+        record: &Record,
+        transaction_type: Option<UpdateTransactionType>,
+    ) -> Result<UpdatePayload, KSMRError> {
+        if record.record_key_bytes.is_empty() {
+            return Err(KSMRError::RecordNotFound(format!(
+                "record {} is missing its encryption key; fetch it via get_secrets before saving",
+                record.uid
+            )));
+        }
+        let record_json = serde_json::to_vec(record).map_err(|e| KSMRError::Serialization(e.to_string()))?;
+        let encrypted_data = crypto::encrypt_aes_gcm_with(self.rng(), &record.record_key_bytes, &record_json)?;
+        Ok(UpdatePayload {
+            client_version: KEEPER_SECRETS_MANAGER_SDK_CLIENT_ID.to_string(),
+            client_id: self
+                .config()
+                .get(ConfigKey::ClientId)
+                .ok_or_else(|| KSMRError::Config("client is not bound: missing client id".into()))?,
+            record_uid: record.uid.clone(),
+            revision: record.revision,
+            data: bytes_to_base64(&encrypted_data),
+            transaction_type: transaction_type.map(|t| t.as_str().to_string()),
+        })
+    }
+
+    /// Fetches `record_uid` fresh and reports whether the share it came
+    /// through grants edit rights, so a caller like [`SecretsManager::save`]
+    /// or [`SecretsManager::create_secret`] can fail fast with a clear
+    /// message before starting a multi-step rotation, instead of only
+    /// discovering a read-only binding from a rejected `update_secret` call.
+    ///
+    /// The gateway doesn't report `isEditable` on every share (see
+    /// [`Record::is_editable`]); when it's absent, this returns `true`
+    /// rather than guessing the share is read-only - an absent flag isn't
+    /// evidence of a restriction, and a false "yes" here still fails safely
+    /// at the real write later, while a false "no" would block good writes.
+    pub fn can_write(&self, record_uid: &str) -> Result<bool, KSMRError> {
+        let record = self
+            .get_secrets(Some(vec![record_uid.to_string()]))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| KSMRError::RecordNotFound(record_uid.to_string()))?;
+        Ok(record.is_editable.unwrap_or(true))
+    }
+
+    /// Saves updated field values for a single, previously-fetched `record`.
+    pub fn save(&self, record: &Record) -> Result<(), KSMRError> {
+        let payload = self.prepare_update_payload(record, None)?;
+        self.post_query("update_secret", &payload)?;
+        Ok(())
+    }
+
+    /// Saves `records` one at a time (the gateway has no batched update
+    /// endpoint), returning a [`SaveResult`] per record so a failure on one
+    /// record doesn't abort updates already queued for the rest of the batch.
+    ///
+    /// Pass a [`CancellationToken`] to be able to stop the batch early (e.g.
+    /// because the caller that asked for this sync has disconnected): once
+    /// it's cancelled, no further records are saved, and the returned vec is
+    /// shorter than `records` rather than padded with synthetic failures for
+    /// the ones that were skipped.
+    pub fn save_all(
+        &self,
+        records: &[Record],
+        transaction_type: Option<UpdateTransactionType>,
+        cancellation: Option<&CancellationToken>,
+    ) -> Vec<SaveResult> {
+        records
+            .iter()
+            .take_while(|_| !cancellation.is_some_and(CancellationToken::is_cancelled))
+            .map(|record| {
+                let outcome = self
+                    .prepare_update_payload(record, transaction_type)
+                    .and_then(|payload| self.post_query("update_secret", &payload));
+                SaveResult { uid: record.uid.clone(), error: outcome.err() }
+            })
+            .collect()
+    }
+
+    /// Fetches `record_uid`, updates the single field matching `field_selector`
+    /// to `value`, and saves - the fetch/mutate/save sequence a caller would
+    /// otherwise write by hand for a one-field rotation. Fails with
+    /// [`KSMRError::RecordNotFound`] if the record doesn't exist or has no
+    /// field matching `field_selector`.
+    pub fn update_field_value(
+        &self,
+        record_uid: &str,
+        field_selector: FieldSelector,
+        value: serde_json::Value,
+        transaction_type: Option<UpdateTransactionType>,
+    ) -> Result<(), KSMRError> {
+        let mut record = self
+            .get_secrets(Some(vec![record_uid.to_string()]))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| KSMRError::RecordNotFound(record_uid.to_string()))?;
+        let field = record.field_by_selector_mut(&field_selector).ok_or_else(|| {
+            KSMRError::RecordNotFound(format!(
+                "record {record_uid} has no field matching {field_selector:?}"
+            ))
+        })?;
+        field.value = vec![value];
+        let payload = self.prepare_update_payload(&record, transaction_type)?;
+        self.post_query("update_secret", &payload)?;
+        Ok(())
+    }
+
+    /// Decrypts `record_blob` (AES-256-GCM, as returned by the server) with `record_key`.
+    pub fn decrypt_record(&self, record_key: &[u8], record_blob: &[u8]) -> Result<Record, KSMRError> {
+        let plaintext = crypto::decrypt_aes_gcm(record_key, record_blob)?;
+        let json_str = bytes_to_string(&plaintext)?;
+        crate::utils::json_to_dict::<Record>(&json_str)
+    }
+
+    /// Unwraps a shared folder's AES-256-GCM key, which is wrapped under the
+    /// application key (top-level shared folder) or, through repeated calls,
+    /// under a parent folder's own key (nested shared folder). Exposed on its
+    /// own so callers that obtained a folder key and its wrapping key
+    /// out-of-band can decrypt it without going through a full `get_secret` call.
+    pub fn decrypt_folder_key(
+        &self,
+        wrapping_key: &[u8],
+        encrypted_folder_key: &[u8],
+    ) -> Result<Vec<u8>, KSMRError> {
+        crypto::decrypt_aes_gcm(wrapping_key, encrypted_folder_key)
+    }
+
+    /// Decrypts a folder's name, AES-256-GCM-encrypted under its own
+    /// (already-unwrapped) folder key.
+    pub fn decrypt_folder_name(&self, folder_key: &[u8], encrypted_name: &[u8]) -> Result<String, KSMRError> {
+        let name_plain = crypto::decrypt_aes_gcm(folder_key, encrypted_name)?;
+        bytes_to_string(&name_plain)
+    }
+
+    /// Decrypts a folder's name and key from the server's wire format.
+    pub fn decrypt_folder(&self, app_key: &[u8], encrypted_folder_key: &[u8], encrypted_name: &[u8]) -> Result<Folder, KSMRError> {
+        let folder_key = self.decrypt_folder_key(app_key, encrypted_folder_key)?;
+        let name = self.decrypt_folder_name(&folder_key, encrypted_name)?;
+        Ok(Folder { folder_uid: String::new(), name, parent_uid: None, folder_key_bytes: folder_key })
+    }
+
+    /// Decrypts a single wire-format record with the given (already-decrypted) secret key,
+    /// which is either the shared folder's key or the application key, depending on
+    /// whether the record ships its own wrapped `recordKey`. `lossy` controls how the
+    /// decrypted bytes are turned into a string: see
+    /// [`SecretsManager::fetch_and_decrypt_secrets_lossy`].
+    /// `fallback_key` is a second `(name, key)` this record's wrapped
+    /// `recordKey` can be tried under if `secret_key` doesn't unwrap it - the
+    /// gateway can list a record under a folder it was re-shared into while
+    /// its `recordKey` is still wrapped under the application key it was
+    /// originally shared with directly, or vice versa. When the fallback is
+    /// the one that actually works, that's noted in `warnings` (by record
+    /// uid and which key recovered it) rather than silently swapping keys,
+    /// since it's a sign the response's folder/record pairing doesn't match
+    /// how the record is actually keyed.
+    /// Unwraps `wire`'s per-record key (trying `fallback_key` if `secret_key`
+    /// doesn't work - see the `fallback_key` doc below) and decrypts its
+    /// `data` blob, stopping short of parsing the plaintext into any
+    /// particular shape. [`SecretsManager::decrypt_wire_record`] parses it as
+    /// a [`Record`]; [`SecretsManager::list_attachments`] parses the same
+    /// kind of blob for a file record as [`FileRecordMeta`] instead.
+    fn decrypt_wire_record_plaintext(
+        &self,
+        wire: &WireRecord,
+        secret_key: &[u8],
+        fallback_key: Option<(&str, &[u8])>,
+        warnings: &mut Vec<String>,
+    ) -> Result<(Vec<u8>, Vec<u8>), KSMRError> {
+        let record_key_bytes = match &wire.record_key {
+            Some(encrypted) => {
+                let wrapped = base64_to_bytes(encrypted)?;
+                match crypto::decrypt_aes_gcm(secret_key, &wrapped) {
+                    Ok(key) => key,
+                    Err(primary_err) => {
+                        let recovered = fallback_key.and_then(|(name, key)| {
+                            crypto::decrypt_aes_gcm(key, &wrapped).ok().map(|bytes| (name, bytes))
+                        });
+                        match recovered {
+                            Some((name, key_bytes)) => {
+                                warnings.push(format!(
+                                    "record {} did not decrypt under its expected key; recovered using the {name} key instead",
+                                    wire.record_uid
+                                ));
+                                key_bytes
+                            }
+                            None => return Err(primary_err),
+                        }
+                    }
+                }
+            }
+            None => secret_key.to_vec(),
+        };
+        let data_bytes = base64_to_bytes(&wire.data)?;
+        let plaintext = crypto::decrypt_aes_gcm(&record_key_bytes, &data_bytes)?;
+        Ok((record_key_bytes, plaintext))
+    }
+
+    fn decrypt_wire_record(
+        &self,
+        wire: &WireRecord,
+        secret_key: &[u8],
+        fallback_key: Option<(&str, &[u8])>,
+        folder_uid: Option<&str>,
+        lossy: bool,
+        warnings: &mut Vec<String>,
+    ) -> Result<Record, KSMRError> {
+        let (record_key_bytes, plaintext) =
+            self.decrypt_wire_record_plaintext(wire, secret_key, fallback_key, warnings)?;
+        let mut record: Record = if lossy {
+            let json_str = crate::utils::bytes_to_string_lossy(&plaintext);
+            crate::utils::json_to_dict(&json_str)?
+        } else {
+            let json_str = bytes_to_string(&plaintext)?;
+            crate::utils::json_to_dict(&json_str)?
+        };
+        record.uid = wire.record_uid.clone();
+        record.folder_uid = folder_uid.map(|s| s.to_string());
+        record.revision = wire.revision;
+        record.is_editable = wire.is_editable;
+        record.record_key_bytes = record_key_bytes;
+        Ok(record)
+    }
+
+    /// Fetches and fully decrypts the `get_secret` response: records, shared folders and,
+    /// on the very first call after binding, the application key itself.
+    pub fn fetch_and_decrypt_secrets(&self, record_filter: Option<&[String]>) -> Result<SecretsManagerResponse, KSMRError> {
+        self.fetch_and_decrypt_secrets_inner(record_filter, false)
+    }
+
+    /// Like [`SecretsManager::fetch_and_decrypt_secrets`], but decodes each record's
+    /// decrypted bytes with [`crate::utils::bytes_to_string_lossy`] instead of failing
+    /// the whole batch when one record has a stray non-UTF-8 byte (e.g. a legacy
+    /// import). Prefer the strict version by default; reach for this one only once
+    /// you've hit a record you otherwise can't read at all.
+    pub fn fetch_and_decrypt_secrets_lossy(
+        &self,
+        record_filter: Option<&[String]>,
+    ) -> Result<SecretsManagerResponse, KSMRError> {
+        self.fetch_and_decrypt_secrets_inner(record_filter, true)
+    }
+
+    /// Runs a `get_secret` query and unwraps the application key needed to
+    /// decrypt its response, without decrypting any record or folder the
+    /// response carries - the shared first half of
+    /// [`SecretsManager::fetch_and_decrypt_secrets_inner`] and
+    /// [`SecretsManager::list_attachments`], which decrypt what comes back
+    /// into different shapes ([`Record`] vs [`FileRecordMeta`]).
+    fn fetch_wire_response(
+        &self,
+        record_filter: Option<&[String]>,
+    ) -> Result<(GetSecretsResponseWire, Vec<u8>, bool, ResponseSource), KSMRError> {
+        let payload = self.prepare_get_payload(record_filter)?;
+        let (decrypted, source) = self.post_query_reporting_source("get_secret", &payload);
+        let decrypted = decrypted?;
+        let wire: GetSecretsResponseWire = crate::utils::json_to_dict(&bytes_to_string(&decrypted)?)?;
+
+        let mut just_bound = false;
+        let secret_key = if let Some(encrypted_app_key) = &wire.encrypted_app_key {
+            just_bound = true;
+            let client_key_token = self
+                .config()
+                .get(ConfigKey::ClientKey)
+                .ok_or_else(|| KSMRError::Config("missing one-time token needed to unwrap the app key".into()))?;
+            let client_key = url_safe_str_to_bytes(&client_key_token)?;
+            let app_key = crypto::decrypt_aes_gcm(&client_key, &url_safe_str_to_bytes(encrypted_app_key)?)?;
+            self.config().set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+            // Unlike `bind_if_needed`'s own cleanup of a leftover token from a
+            // previous process, this one is deliberately *not* deleted here: if
+            // the application gets re-shared or its app key rotated server-side
+            // while this `SecretsManager` stays bound (no restart, same
+            // client id), a later `get_secret` response can carry a fresh
+            // `encryptedAppKey` wrapped under this same client key, and this
+            // branch needs to be able to unwrap that one too. Without this, a
+            // rotation mid-session would permanently fail every decryption
+            // until the process was restarted with a brand-new token.
+            if let Some(owner_key) = &wire.app_owner_public_key {
+                self.config()
+                    .set(ConfigKey::OwnerPublicKey, bytes_to_base64(&url_safe_str_to_bytes(owner_key)?));
+            }
+            app_key
+        } else {
+            base64_to_bytes(
+                &self
+                    .config()
+                    .get(ConfigKey::AppKey)
+                    .ok_or_else(|| KSMRError::Config("client is not bound: missing app key".into()))?,
+            )?
+        };
+
+        Ok((wire, secret_key, just_bound, source))
+    }
+
+    fn fetch_and_decrypt_secrets_inner(
+        &self,
+        record_filter: Option<&[String]>,
+        lossy: bool,
+    ) -> Result<SecretsManagerResponse, KSMRError> {
+        let (wire, secret_key, just_bound, source) = self.fetch_wire_response(record_filter)?;
+        self.parse_wire_response(wire, secret_key, just_bound, source, lossy)
+    }
+
+    /// Decrypts a [`GetSecretsResponseWire`] (fetched live, or read back out of
+    /// [`ClientOptions::enable_disaster_recovery_cache`]'s cache by
+    /// [`SecretsManager::inspect_cache`]) into a full [`SecretsManagerResponse`].
+    /// Split out of [`SecretsManager::fetch_and_decrypt_secrets_inner`] so both
+    /// callers share the same record/folder decryption instead of drifting apart.
+    fn parse_wire_response(
+        &self,
+        wire: GetSecretsResponseWire,
+        secret_key: Vec<u8>,
+        just_bound: bool,
+        source: ResponseSource,
+        lossy: bool,
+    ) -> Result<SecretsManagerResponse, KSMRError> {
+        let mut warnings = wire.warnings;
+        let mut records = Vec::with_capacity(wire.records.len());
+        for r in &wire.records {
+            records.push(self.decrypt_wire_record(r, &secret_key, None, None, lossy, &mut warnings)?);
+        }
+
+        let mut folders = Vec::with_capacity(wire.folders.len());
+        for f in &wire.folders {
+            let folder_key = self.decrypt_folder_key(&secret_key, &base64_to_bytes(&f.folder_key)?)?;
+            for r in &f.records {
+                // A partially-shared or malformed folder can hold records this
+                // app's folder key can't unwrap (e.g. a record re-keyed to a
+                // different share the app never received). One bad record in
+                // an otherwise-good folder shouldn't sink the whole fetch, so
+                // it's skipped and reported here instead of propagated with `?`.
+                // Before giving up, the app's own key is tried as a fallback
+                // scheme - see `decrypt_wire_record`'s `fallback_key` doc.
+                match self.decrypt_wire_record(
+                    r,
+                    &folder_key,
+                    Some(("application", &secret_key)),
+                    Some(&f.folder_uid),
+                    lossy,
+                    &mut warnings,
+                ) {
+                    Ok(record) => records.push(record),
+                    Err(e) => warnings.push(format!(
+                        "skipped record {} in folder {}: {e}",
+                        r.record_uid, f.folder_uid
+                    )),
+                }
+            }
+            let name = match &f.data {
+                Some(encrypted) => {
+                    let plaintext = crypto::decrypt_aes_gcm(&folder_key, &base64_to_bytes(encrypted)?)?;
+                    let meta: FolderMeta = crate::utils::json_to_dict(&bytes_to_string(&plaintext)?)?;
+                    meta.name
+                }
+                None => String::new(),
+            };
+            folders.push(Folder {
+                folder_uid: f.folder_uid.clone(),
+                name,
+                parent_uid: f.parent_uid.clone(),
+                folder_key_bytes: folder_key,
+            });
+        }
+
+        let app_data = match &wire.app_data {
+            Some(encrypted) => Some(crate::dto::decrypt_app_data(encrypted, &secret_key)?),
+            None => None,
+        };
+
+        Ok(SecretsManagerResponse {
+            records,
+            folders,
+            app_data,
+            expires_on: wire.expires_on,
+            warnings,
+            just_bound,
+            source,
+        })
+    }
+
+    /// Retrieves all records the application can access, or just `uids` if given.
+    pub fn get_secrets(&self, uids: Option<Vec<String>>) -> Result<Vec<Record>, KSMRError> {
+        let uids = uids.as_deref();
+        let mut response = self.fetch_and_decrypt_secrets(uids)?;
+        if response.just_bound {
+            // The first response only carries the wrapped app key; re-fetch now that
+            // it's been unwrapped and stored so we actually get the requested records.
+            // `uids` is just borrowed above, so no clone is needed for this retry.
+            response = self.fetch_and_decrypt_secrets(uids)?;
+        }
+        Ok(response.records)
+    }
+
+    /// Like [`SecretsManager::get_secrets`], but tolerant of a record whose
+    /// decrypted bytes contain a non-UTF-8 byte (e.g. a legacy import): that
+    /// record comes back with a replacement character (U+FFFD) in place of
+    /// the bad byte instead of the whole call failing. Use
+    /// [`SecretsManager::get_secrets`] unless you've already hit a record it
+    /// can't read.
+    pub fn get_secrets_lossy(&self, uids: Option<Vec<String>>) -> Result<Vec<Record>, KSMRError> {
+        let uids = uids.as_deref();
+        let mut response = self.fetch_and_decrypt_secrets_lossy(uids)?;
+        if response.just_bound {
+            response = self.fetch_and_decrypt_secrets_lossy(uids)?;
+        }
+        Ok(response.records)
+    }
+
+    /// Like [`SecretsManager::get_secrets`], but driven by a [`QueryOptions`]
+    /// built fluently (`QueryOptions::builder().records(&[..]).folders(&[..]).build()`)
+    /// instead of a bare, easy-to-mix-up positional argument. Record uids are
+    /// sent to the gateway as a server-side filter; folder uids are applied
+    /// afterwards against each record's `folder_uid`, since the gateway has
+    /// no folder filter of its own.
+    pub fn get_secrets_with_options(&self, options: &QueryOptions) -> Result<Vec<Record>, KSMRError> {
+        let record_filter = if options.record_uids.is_empty() { None } else { Some(options.record_uids.clone()) };
+        let records = self.get_secrets(record_filter)?;
+        if options.folder_uids.is_empty() {
+            return Ok(records);
+        }
+        Ok(records
+            .into_iter()
+            .filter(|r| r.folder_uid.as_deref().is_some_and(|uid| options.folder_uids.iter().any(|f| f == uid)))
+            .collect())
+    }
+
+    /// Returns the first record titled `title`, or `None` if none matches.
+    ///
+    /// The `get_secret` wire call's `requestedRecords` filter (see
+    /// [`GetPayload`]) only accepts record uids - the gateway has no
+    /// server-side title filter - so there is no way to ask for a title
+    /// match without first downloading every record this application can
+    /// access. This is therefore a client-side filter over
+    /// [`SecretsManager::get_secrets`], not a targeted fetch; for a vault
+    /// with many records, prefer [`SecretsManager::get_secrets_with_options`]
+    /// (or plain [`SecretsManager::get_secrets`] with known uids) when the
+    /// uid is already known.
+    pub fn get_secret_by_title(&self, title: &str) -> Result<Option<Record>, KSMRError> {
+        Ok(self.get_secrets(None)?.into_iter().find(|r| r.title == title))
+    }
+
+    /// Fetches every record this application can access and keeps only the
+    /// ones `predicate` accepts, the same fetch-then-filter [`SecretsManager::get_secret_by_title`]
+    /// does for a title match, generalized to an arbitrary client-side
+    /// condition. Like that method, this downloads the whole vault first -
+    /// the gateway has no predicate of its own to push this down to - so
+    /// prefer [`SecretsManager::get_secrets_with_options`] or plain
+    /// [`SecretsManager::get_secrets`] when the uids are already known.
+    pub fn get_secrets_filtered(
+        &self,
+        predicate: impl Fn(&Record) -> bool,
+    ) -> Result<Vec<Record>, KSMRError> {
+        Ok(self.get_secrets(None)?.into_iter().filter(predicate).collect())
+    }
+
+    /// Like [`SecretsManager::get_secret_by_title`], but resolves several
+    /// titles off a single fetch instead of one per title - useful for a
+    /// startup step that needs a handful of well-known records by name.
+    /// Titles aren't unique, so each maps to every record that matched it;
+    /// a title with no match is simply absent from the returned map rather
+    /// than present with an empty `Vec`.
+    pub fn get_secrets_by_titles(
+        &self,
+        titles: &[String],
+    ) -> Result<std::collections::HashMap<String, Vec<Record>>, KSMRError> {
+        let mut by_title: std::collections::HashMap<String, Vec<Record>> = std::collections::HashMap::new();
+        for record in self.get_secrets(None)? {
+            if titles.contains(&record.title) {
+                by_title.entry(record.title.clone()).or_default().push(record);
+            }
+        }
+        Ok(by_title)
+    }
+
+    /// Fetches every record this application can access and returns only
+    /// the titles shared by more than one of them, each mapped to every uid
+    /// that carries it, for spotting a vault whose title-based lookups
+    /// ([`SecretsManager::get_secret_by_title`], notation by title) are
+    /// ambiguous. A title held by exactly one record is simply absent from
+    /// the returned map.
+    pub fn find_duplicate_titles(&self) -> Result<std::collections::HashMap<String, Vec<String>>, KSMRError> {
+        let mut uids_by_title: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
+        for record in self.get_secrets(None)? {
+            uids_by_title.entry(record.title).or_default().push(record.uid);
+        }
+        uids_by_title.retain(|_, uids| uids.len() > 1);
+        Ok(uids_by_title)
+    }
+
+    /// Fetches every record this application can access and tallies how many
+    /// exist of each `record_type`, e.g. to drive a type-filter dropdown
+    /// without a caller iterating every record and counting by hand. Sorted
+    /// alphabetically by type name rather than by count, since a dropdown
+    /// wants a stable, predictable order more than it wants to highlight the
+    /// most common type first.
+    pub fn list_record_types(&self) -> Result<Vec<(String, usize)>, KSMRError> {
+        let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        for record in self.get_secrets(None)? {
+            *counts.entry(record.record_type).or_insert(0) += 1;
+        }
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(counts)
+    }
+
+    /// Fetches every record this application can access and returns the ones
+    /// with an `expirationDate` or `cardExpirationDate` field (standard or
+    /// custom) whose value falls within `within` of now, so a caller can
+    /// power a "credentials expiring soon" alert without parsing each
+    /// record's date fields by hand. A record is included if *any* matching
+    /// field is within the window, including one that has already expired,
+    /// since an alert that only fires before the deadline and falls silent
+    /// after it would be worse than one that keeps firing until renewed.
+    ///
+    /// Date fields are read as epoch milliseconds, the shape the vault
+    /// stores a date-typed field's value as; a record whose only matching
+    /// field isn't a plain number is skipped rather than erroring, since
+    /// this is meant as a best-effort scan across a whole vault, not a
+    /// strict validator.
+    pub fn get_expiring_secrets(&self, within: Duration) -> Result<Vec<Record>, KSMRError> {
+        let threshold_millis = crate::utils::now_milliseconds() as i64 + within.as_millis() as i64;
+        Ok(self
+            .get_secrets(None)?
+            .into_iter()
+            .filter(|record| Self::has_expiration_before(record, threshold_millis))
+            .collect())
+    }
+
+    /// Pulled out of [`SecretsManager::get_expiring_secrets`] as its own
+    /// function so the field-scanning logic can be unit-tested against a
+    /// plain [`Record`] without a fake-server round trip.
+    fn has_expiration_before(record: &Record, threshold_millis: i64) -> bool {
+        record
+            .fields
+            .iter()
+            .chain(record.custom.iter())
+            .filter(|f| f.field_type == "expirationDate" || f.field_type == "cardExpirationDate")
+            .flat_map(|f| f.value.iter())
+            .filter_map(|v| v.as_i64())
+            .any(|millis| millis <= threshold_millis)
+    }
+
+    /// Returns every shared folder the application can access. A folder's
+    /// `name` is only populated for folders created through
+    /// [`SecretsManager::create_folder`] - others come back with an empty name,
+    /// since the gateway doesn't expose their encrypted name to this protocol.
+    pub fn get_folders(&self) -> Result<Vec<Folder>, KSMRError> {
+        let mut response = self.fetch_and_decrypt_secrets(None)?;
+        if response.just_bound {
+            response = self.fetch_and_decrypt_secrets(None)?;
+        }
+        Ok(response.folders)
+    }
+
+    /// Like [`SecretsManager::get_secrets`] and [`SecretsManager::get_folders`]
+    /// combined into one fetch, but without either's loss of structure:
+    /// [`SecretsManager::get_secrets`] flattens every folder's records into a
+    /// single list, and [`SecretsManager::get_folders`] has no records at
+    /// all. This nests each folder's records under it and nests folders
+    /// under their parent, so a caller doesn't have to re-fetch and
+    /// reconstruct the hierarchy itself from two separate calls.
+    ///
+    /// A folder whose parent isn't in this application's share (or has none)
+    /// is treated as a root of the tree. Records shared directly, with no
+    /// `folder_uid` at all, come back in [`VaultSnapshot::unfiled_records`]
+    /// rather than under any folder node.
+    pub fn get_vault_snapshot(&self) -> Result<VaultSnapshot, KSMRError> {
+        let mut response = self.fetch_and_decrypt_secrets(None)?;
+        if response.just_bound {
+            response = self.fetch_and_decrypt_secrets(None)?;
+        }
+
+        let mut records_by_folder: std::collections::HashMap<String, Vec<Record>> = std::collections::HashMap::new();
+        let mut unfiled_records = Vec::new();
+        for record in response.records {
+            match &record.folder_uid {
+                Some(folder_uid) => records_by_folder.entry(folder_uid.clone()).or_default().push(record),
+                None => unfiled_records.push(record),
+            }
+        }
+
+        let mut nodes_by_parent: std::collections::HashMap<Option<String>, Vec<Folder>> =
+            std::collections::HashMap::new();
+        for folder in response.folders {
+            nodes_by_parent.entry(folder.parent_uid.clone()).or_default().push(folder);
+        }
+        let all_folder_uids: std::collections::HashSet<String> =
+            nodes_by_parent.values().flatten().map(|f| f.folder_uid.clone()).collect();
+
+        fn build_children(
+            parent_uid: Option<&str>,
+            nodes_by_parent: &mut std::collections::HashMap<Option<String>, Vec<Folder>>,
+            records_by_folder: &mut std::collections::HashMap<String, Vec<Record>>,
+        ) -> Vec<VaultFolderNode> {
+            let Some(folders) = nodes_by_parent.remove(&parent_uid.map(str::to_string)) else {
+                return Vec::new();
+            };
+            folders
+                .into_iter()
+                .map(|folder| {
+                    let records = records_by_folder.remove(&folder.folder_uid).unwrap_or_default();
+                    let children = build_children(Some(&folder.folder_uid), nodes_by_parent, records_by_folder);
+                    VaultFolderNode { folder, records, children }
+                })
+                .collect()
+        }
+
+        let folders = build_children(None, &mut nodes_by_parent, &mut records_by_folder);
+        // A folder left in `nodes_by_parent` keyed under a parent_uid that
+        // isn't any folder in this application's share is a genuine orphan
+        // root - treated as a root too rather than dropped. Its own
+        // descendants (if any) are still keyed under `Some(orphan.folder_uid)`
+        // and would otherwise never get visited, so each root is walked with
+        // the same `build_children` recursion the main tree uses instead of
+        // being given empty `children` - a multi-level orphan subtree nests
+        // correctly instead of flattening into disconnected top-level nodes.
+        let orphan_root_keys: Vec<String> = nodes_by_parent
+            .keys()
+            .filter_map(|key| key.clone())
+            .filter(|parent_uid| !all_folder_uids.contains(parent_uid))
+            .collect();
+        let mut orphans = Vec::new();
+        for key in orphan_root_keys {
+            let Some(roots) = nodes_by_parent.remove(&Some(key)) else { continue };
+            for folder in roots {
+                let records = records_by_folder.remove(&folder.folder_uid).unwrap_or_default();
+                let children = build_children(Some(&folder.folder_uid), &mut nodes_by_parent, &mut records_by_folder);
+                orphans.push(VaultFolderNode { folder, records, children });
+            }
+        }
+
+        let mut folders = folders;
+        folders.extend(orphans);
+
+        Ok(VaultSnapshot { folders, unfiled_records })
+    }
+
+    /// Returns a flat summary of every shared folder this application can
+    /// access, with each one's `record_count` - how many records list it as
+    /// their `folder_uid` - tallied from the same fetch, rather than a
+    /// caller re-deriving the count from [`SecretsManager::get_vault_snapshot`]
+    /// or [`SecretsManager::get_secrets`] by hand. Unlike
+    /// [`SecretsManager::get_vault_snapshot`], there's no nesting here - this
+    /// is meant for a report or dropdown that just wants counts per folder,
+    /// not the hierarchy.
+    pub fn folder_summary(&self) -> Result<Vec<FolderSummary>, KSMRError> {
+        let mut response = self.fetch_and_decrypt_secrets(None)?;
+        if response.just_bound {
+            response = self.fetch_and_decrypt_secrets(None)?;
+        }
+
+        let mut record_counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        for record in &response.records {
+            if let Some(folder_uid) = &record.folder_uid {
+                *record_counts.entry(folder_uid.clone()).or_insert(0) += 1;
+            }
+        }
+
+        Ok(response
+            .folders
+            .into_iter()
+            .map(|folder| FolderSummary {
+                record_count: record_counts.get(&folder.folder_uid).copied().unwrap_or(0),
+                folder_uid: folder.folder_uid,
+                name: folder.name,
+                parent_uid: folder.parent_uid,
+            })
+            .collect())
+    }
+
+    /// Finds `folder_uid`'s already-unwrapped key among
+    /// [`SecretsManager::get_folders`]'s result, the value
+    /// [`SecretsManager::create_secret`], [`SecretsManager::create_note`] and
+    /// [`SecretsManager::create_secret_with_files`] need for their
+    /// `folder_key` argument, so a caller doesn't have to search that list
+    /// by hand before every create.
+    ///
+    /// Returns [`KSMRError::FolderNotFound`] both when `folder_uid` doesn't
+    /// exist at all and when this application's share just doesn't include
+    /// it - including the common case of an application that was shared
+    /// records directly rather than through any folder, which has no
+    /// folders at all to search. A record can only be created inside a
+    /// folder through this protocol, so there is no folder key to return
+    /// for a direct share either way; distinguishing "wrong uid" from "no
+    /// folder share exists" isn't possible from this response, since the
+    /// gateway doesn't say which folders exist but weren't shared to this
+    /// application.
+    pub fn get_folder_key(&self, folder_uid: &str) -> Result<Vec<u8>, KSMRError> {
+        self.get_folders()?
+            .into_iter()
+            .find(|f| f.folder_uid == folder_uid)
+            .map(|f| f.folder_key_bytes)
+            .ok_or_else(|| KSMRError::FolderNotFound(folder_uid.to_string()))
+    }
+
+    /// Creates a new folder named `name` under `parent_uid`, whose decrypted
+    /// key is `parent_key` (typically resolved from a prior `get_folders` or
+    /// `get_secrets` call). Returns the new folder's uid.
+    pub fn create_folder(&self, parent_uid: &str, parent_key: &[u8], name: &str) -> Result<String, KSMRError> {
+        if parent_key.is_empty() {
+            return Err(KSMRError::FolderNotFound(parent_uid.to_string()));
+        }
+        let folder_uid = bytes_to_url_safe_str(&generate_random_bytes_with(self.rng(), 16));
+        let folder_key = generate_encryption_key_bytes_with(self.rng());
+
+        let meta_json = serde_json::to_vec(&FolderMeta { name: name.to_string() })
+            .map_err(|e| KSMRError::Serialization(e.to_string()))?;
+        let encrypted_data = crypto::encrypt_aes_gcm_with(self.rng(), &folder_key, &meta_json)?;
+        let encrypted_folder_key = crypto::encrypt_aes_gcm_with(self.rng(), parent_key, &folder_key)?;
+
+        let payload = CreateFolderPayload {
+            client_version: KEEPER_SECRETS_MANAGER_SDK_CLIENT_ID.to_string(),
+            client_id: self
+                .config()
+                .get(ConfigKey::ClientId)
+                .ok_or_else(|| KSMRError::Config("client is not bound: missing client id".into()))?,
+            folder_uid: folder_uid.clone(),
+            parent_uid: parent_uid.to_string(),
+            parent_key: bytes_to_base64(&encrypted_folder_key),
+            data: bytes_to_base64(&encrypted_data),
+        };
+        self.post_query("create_folder", &payload)?;
+        Ok(folder_uid)
+    }
+
+    /// Like [`SecretsManager::create_folder`], but idempotent: if a folder
+    /// named `name` already exists directly under `parent_uid`, returns its
+    /// uid instead of creating a duplicate. Safe to call repeatedly from
+    /// provisioning scripts that re-run against an already-provisioned vault.
+    pub fn create_folder_if_absent(
+        &self,
+        parent_uid: &str,
+        parent_key: &[u8],
+        name: &str,
+    ) -> Result<String, KSMRError> {
+        let existing = self
+            .get_folders()?
+            .into_iter()
+            .find(|f| f.parent_uid.as_deref() == Some(parent_uid) && f.name == name);
+        match existing {
+            Some(folder) => Ok(folder.folder_uid),
+            None => self.create_folder(parent_uid, parent_key, name),
+        }
+    }
+
+    /// Renames `folder` to `new_name`, re-encrypting its metadata under its
+    /// existing key. `folder` is typically one returned from a prior
+    /// [`SecretsManager::get_folders`] call, so its `folder_key_bytes` is
+    /// already populated.
+    pub fn update_folder(&self, folder: &Folder, new_name: &str) -> Result<(), KSMRError> {
+        if folder.folder_key_bytes.is_empty() {
+            return Err(KSMRError::FolderNotFound(folder.folder_uid.clone()));
+        }
+        let meta_json = serde_json::to_vec(&FolderMeta { name: new_name.to_string() })
+            .map_err(|e| KSMRError::Serialization(e.to_string()))?;
+        let encrypted_data = crypto::encrypt_aes_gcm_with(self.rng(), &folder.folder_key_bytes, &meta_json)?;
+
+        let payload = UpdateFolderPayload {
+            client_version: KEEPER_SECRETS_MANAGER_SDK_CLIENT_ID.to_string(),
+            client_id: self
+                .config()
+                .get(ConfigKey::ClientId)
+                .ok_or_else(|| KSMRError::Config("client is not bound: missing client id".into()))?,
+            folder_uid: folder.folder_uid.clone(),
+            data: bytes_to_base64(&encrypted_data),
+        };
+        self.post_query("update_folder", &payload)?;
+        Ok(())
+    }
+
+    /// Like [`SecretsManager::update_folder`], but resolves the folder by
+    /// name instead of requiring the caller to already have its uid and key
+    /// in hand - friendlier for ops scripts that think in names. Errors if
+    /// no folder is named `current_name`, or if more than one is, since
+    /// guessing which one to rename would be worse than asking the caller to
+    /// disambiguate.
+    pub fn rename_folder_by_name(&self, current_name: &str, new_name: &str) -> Result<(), KSMRError> {
+        let mut matches: Vec<Folder> =
+            self.get_folders()?.into_iter().filter(|f| f.name == current_name).collect();
+        match matches.len() {
+            0 => Err(KSMRError::FolderNotFound(current_name.to_string())),
+            1 => self.update_folder(&matches.remove(0), new_name),
+            n => Err(KSMRError::Other(format!(
+                "{n} folders are named {current_name:?}; rename by uid with update_folder instead"
+            ))),
+        }
+    }
+
+    /// Returns uid/title/type/folder for every record the app can access, without
+    /// handing back any decrypted field values - cheaper and lower-risk for
+    /// audit tooling that only needs to know what is shared.
+    ///
+    /// The Keeper gateway has no metadata-only endpoint, so this is a
+    /// client-side variant: it still fetches and decrypts full records
+    /// internally, but immediately discards field values before returning,
+    /// so callers never see the actual secrets.
+    pub fn list_record_metadata(&self) -> Result<Vec<RecordMeta>, KSMRError> {
+        Ok(self.get_secrets(None)?.iter().map(RecordMeta::from).collect())
+    }
+
+    /// Returns the field schema for every record type this SDK has a
+    /// built-in definition for - e.g. to drive a generic "create record"
+    /// form that renders the right inputs per type without hardcoding them.
+    ///
+    /// This is a static, client-side list (see [`DefaultRecordType::ALL`]):
+    /// the `get_secret` wire protocol has no endpoint that returns record
+    /// type definitions, so there is no way to fetch enterprise-defined
+    /// custom record types through this SDK. Callers that need those should
+    /// still treat this as a starting point, not the full set the vault
+    /// knows about.
+    pub fn record_type_schemas(&self) -> Vec<RecordTypeSchema> {
+        DefaultRecordType::ALL.iter().map(DefaultRecordType::schema).collect()
+    }
+
+    /// Fetches `uid` plus every record referenced from its `cardRef`/`addressRef`/`fileRef`
+    /// fields, resolved in a map keyed by the referenced uid - the "GraphSync" capability.
+    ///
+    /// A reference that doesn't resolve - the target was deleted, or simply
+    /// isn't shared to this application - doesn't fail the whole call: the
+    /// gateway just silently omits that record from the batch response
+    /// (there's no way to tell the two cases apart from the wire response,
+    /// so this can't either), and its uid is reported in
+    /// [`LinkedRecord::unresolved`] instead of leaving the caller to notice
+    /// its absence from `linked` on their own.
+    pub fn get_secret_with_links(&self, uid: &str) -> Result<LinkedRecord, KSMRError> {
+        let records = self.get_secrets(Some(vec![uid.to_string()]))?;
+        let record = records
+            .into_iter()
+            .next()
+            .ok_or_else(|| KSMRError::RecordNotFound(uid.to_string()))?;
+
+        let ref_uids: Vec<String> = record
+            .fields
+            .iter()
+            .chain(record.custom.iter())
+            .filter(|f| LINKED_FIELD_TYPES.contains(&f.field_type.as_str()))
+            .flat_map(|f| f.value.iter())
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect();
+
+        let mut linked = std::collections::HashMap::new();
+        if !ref_uids.is_empty() {
+            for linked_record in self.get_secrets(Some(ref_uids.clone()))? {
+                linked.insert(linked_record.uid.clone(), linked_record);
+            }
+        }
+        let unresolved: Vec<String> = ref_uids.into_iter().filter(|uid| !linked.contains_key(uid)).collect();
+
+        Ok(LinkedRecord { record, linked, unresolved })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::RecordField;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    fn bound_options(custom: Arc<CustomPostFn>) -> ClientOptions {
+        let config = Arc::new(InMemoryKeyValueStorage::new());
+        config.set(ConfigKey::ClientId, "test-client-id".to_string());
+        config.set(
+            ConfigKey::PrivateKey,
+            bytes_to_base64(&crypto::generate_private_key_ecc().unwrap().to_bytes()),
+        );
+        config.set(ConfigKey::ServerPublicKeyId, DEFAULT_KEY_ID.to_string());
+        config.set(ConfigKey::Hostname, "local.test".to_string());
+        ClientOptions { config, custom_post_function: Some(custom), ..ClientOptions::default() }
+    }
+
+    #[test]
+    fn create_payload_is_deterministic_under_a_seeded_rng() {
+        struct ZeroRng;
+        impl RngProvider for ZeroRng {
+            fn fill_bytes(&self, buf: &mut [u8]) {
+                buf.fill(7);
+            }
+        }
+        let mut options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        options.rng = Arc::new(ZeroRng);
+        let sm = SecretsManager::new(options).unwrap();
+
+        let mut record = RecordCreate::new("login", "Example");
+        record.fields.push(RecordField::new("login", vec![json!("alice")]));
+        let folder_key = vec![1u8; 32];
+
+        let (first, _) = sm.prepare_create_payload("folder-uid", &folder_key, &record).unwrap();
+        let (second, _) = sm.prepare_create_payload("folder-uid", &folder_key, &record).unwrap();
+        assert_eq!(first.record_uid, second.record_uid);
+        assert_eq!(first.data, second.data);
+    }
+
+    #[test]
+    fn post_query_signs_requests_through_a_mock_signer() {
+        struct MockSigner {
+            sign_calls: Arc<std::sync::atomic::AtomicUsize>,
+        }
+        impl crypto::Signer for MockSigner {
+            fn sign(&self, data: &[u8], private_key: &SecretKey) -> Vec<u8> {
+                self.sign_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                crypto::sign(data, private_key)
+            }
+            fn verify(&self, data: &[u8], signature: &[u8], private_key: &SecretKey) -> bool {
+                crypto::verify(data, signature, private_key)
+            }
+        }
+
+        let sign_calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        options.signer = Arc::new(MockSigner { sign_calls: sign_calls.clone() });
+        let sm = SecretsManager::new(options).unwrap();
+
+        sm.post_query("update_secret", &serde_json::json!({})).unwrap();
+        assert_eq!(sign_calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn create_folder_posts_a_folder_record_and_returns_its_uid() {
+        let seen_data: Arc<Mutex<Option<Vec<u8>>>> = Arc::new(Mutex::new(None));
+        let seen_data_clone = seen_data.clone();
+        let options = bound_options(Arc::new(move |_, body, _| {
+            *seen_data_clone.lock().unwrap() = Some(body.to_vec());
+            Ok(KsmHttpResponse { status_code: 200, data: vec![] })
+        }));
+        let sm = SecretsManager::new(options).unwrap();
+        let parent_key = vec![1u8; 32];
+
+        let folder_uid = sm.create_folder("parent-uid", &parent_key, "Infra Secrets").unwrap();
+        assert!(!folder_uid.is_empty());
+        assert!(seen_data.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn create_folder_if_absent_returns_the_existing_folder_without_posting_a_create() {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let app_key = crypto::generate_encryption_key_bytes();
+        let folder_uid = crate::utils::generate_uid();
+        let folder_key = crypto::generate_encryption_key_bytes();
+        let encrypted_folder_key = crypto::encrypt_aes_gcm(&app_key, &folder_key).unwrap();
+        let encrypted_name =
+            crypto::encrypt_aes_gcm(&folder_key, serde_json::json!({"name": "Infra Secrets"}).to_string().as_bytes())
+                .unwrap();
+
+        let create_calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let create_calls_clone = create_calls.clone();
+        let folder_uid_resp = folder_uid.clone();
+        let options = bound_options(Arc::new(move |url, body, _verify| {
+            if url.ends_with("create_folder") {
+                create_calls_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let response_json = serde_json::json!({
+                "folders": [{
+                    "folderUid": folder_uid_resp,
+                    "folderKey": bytes_to_base64(&encrypted_folder_key),
+                    "parentUid": "parent-uid",
+                    "data": bytes_to_base64(&encrypted_name),
+                    "records": [],
+                }],
+            });
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let returned_uid = sm.create_folder_if_absent("parent-uid", &app_key, "Infra Secrets").unwrap();
+        assert_eq!(returned_uid, folder_uid);
+        assert_eq!(create_calls.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn create_folder_if_absent_creates_a_new_folder_when_none_matches() {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let app_key = crypto::generate_encryption_key_bytes();
+        let create_calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let create_calls_clone = create_calls.clone();
+        let options = bound_options(Arc::new(move |url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            if url.ends_with("create_folder") {
+                create_calls_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                return Ok(KsmHttpResponse { status_code: 200, data: vec![] });
+            }
+            let response_json = serde_json::json!({"folders": []});
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let folder_uid = sm.create_folder_if_absent("parent-uid", &app_key, "Infra Secrets").unwrap();
+        assert!(!folder_uid.is_empty());
+        assert_eq!(create_calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn get_folder_key_finds_the_matching_folder_among_several() {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let app_key = crypto::generate_encryption_key_bytes();
+        let wanted_uid = crate::utils::generate_uid();
+        let wanted_key = crypto::generate_encryption_key_bytes();
+        let other_uid = crate::utils::generate_uid();
+        let other_key = crypto::generate_encryption_key_bytes();
+
+        let wanted_uid_resp = wanted_uid.clone();
+        let wanted_key_resp = wanted_key.clone();
+        let other_uid_resp = other_uid.clone();
+        let app_key_for_config = app_key.clone();
+        let options = bound_options(Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let response_json = serde_json::json!({
+                "folders": [
+                    {
+                        "folderUid": other_uid_resp,
+                        "folderKey": bytes_to_base64(&crypto::encrypt_aes_gcm(&app_key, &other_key).unwrap()),
+                        "records": [],
+                    },
+                    {
+                        "folderUid": wanted_uid_resp,
+                        "folderKey": bytes_to_base64(&crypto::encrypt_aes_gcm(&app_key, &wanted_key_resp).unwrap()),
+                        "records": [],
+                    },
+                ],
+            });
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&app_key_for_config));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let folder_key = sm.get_folder_key(&wanted_uid).unwrap();
+        assert_eq!(folder_key, wanted_key);
+    }
+
+    #[test]
+    fn get_folder_key_rejects_a_uid_not_among_this_applications_folders() {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let app_key = crypto::generate_encryption_key_bytes();
+        let options = bound_options(Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let response_json = serde_json::json!({"folders": []});
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let err = sm.get_folder_key("no-such-folder").unwrap_err();
+        assert!(matches!(err, KSMRError::FolderNotFound(_)));
+    }
+
+    #[test]
+    fn update_folder_posts_the_renamed_folder_under_its_own_key() {
+        let seen_data: Arc<Mutex<Option<Vec<u8>>>> = Arc::new(Mutex::new(None));
+        let seen_data_clone = seen_data.clone();
+        let options = bound_options(Arc::new(move |_, body, _| {
+            *seen_data_clone.lock().unwrap() = Some(body.to_vec());
+            Ok(KsmHttpResponse { status_code: 200, data: vec![] })
+        }));
+        let sm = SecretsManager::new(options).unwrap();
+        let folder = Folder {
+            folder_uid: "folder-uid".to_string(),
+            name: "Old Name".to_string(),
+            parent_uid: Some("parent-uid".to_string()),
+            folder_key_bytes: vec![1u8; 32],
+        };
+
+        sm.update_folder(&folder, "New Name").unwrap();
+        assert!(seen_data.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn update_folder_without_a_key_errors() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let sm = SecretsManager::new(options).unwrap();
+        let folder = Folder {
+            folder_uid: "folder-uid".to_string(),
+            name: "Old Name".to_string(),
+            parent_uid: None,
+            folder_key_bytes: vec![],
+        };
+
+        assert!(sm.update_folder(&folder, "New Name").is_err());
+    }
+
+    fn fake_folder_list_options(folder_names: &[&str]) -> ClientOptions {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let app_key = crypto::generate_encryption_key_bytes();
+        let folders: Vec<_> = folder_names
+            .iter()
+            .map(|name| {
+                let folder_key = crypto::generate_encryption_key_bytes();
+                let encrypted_folder_key = crypto::encrypt_aes_gcm(&app_key, &folder_key).unwrap();
+                let encrypted_name =
+                    crypto::encrypt_aes_gcm(&folder_key, serde_json::json!({"name": name}).to_string().as_bytes())
+                        .unwrap();
+                serde_json::json!({
+                    "folderUid": crate::utils::generate_uid(),
+                    "folderKey": bytes_to_base64(&encrypted_folder_key),
+                    "parentUid": "parent-uid",
+                    "data": bytes_to_base64(&encrypted_name),
+                    "records": [],
+                })
+            })
+            .collect();
+
+        let options = bound_options(Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let response_json = serde_json::json!({ "folders": folders });
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+        options
+    }
+
+    #[test]
+    fn rename_folder_by_name_errors_when_no_folder_matches() {
+        let sm = SecretsManager::new(fake_folder_list_options(&["Infra Secrets"])).unwrap();
+        assert!(sm.rename_folder_by_name("Missing", "New Name").is_err());
+    }
+
+    #[test]
+    fn rename_folder_by_name_errors_on_an_ambiguous_name() {
+        let sm = SecretsManager::new(fake_folder_list_options(&["Infra Secrets", "Infra Secrets"])).unwrap();
+        assert!(sm.rename_folder_by_name("Infra Secrets", "New Name").is_err());
+    }
+
+    #[test]
+    fn rename_folder_by_name_renames_the_single_matching_folder() {
+        let sm = SecretsManager::new(fake_folder_list_options(&["Infra Secrets", "Other"])).unwrap();
+        assert!(sm.rename_folder_by_name("Infra Secrets", "New Name").is_ok());
+    }
+
+    #[test]
+    fn create_note_posts_a_single_note_field_record_and_returns_its_uid() {
+        let seen_data: Arc<Mutex<Option<Vec<u8>>>> = Arc::new(Mutex::new(None));
+        let seen_data_clone = seen_data.clone();
+        let options = bound_options(Arc::new(move |_, body, _| {
+            *seen_data_clone.lock().unwrap() = Some(body.to_vec());
+            Ok(KsmHttpResponse { status_code: 200, data: vec![] })
+        }));
+        let sm = SecretsManager::new(options).unwrap();
+        let folder_key = vec![1u8; 32];
+
+        let record_uid = sm.create_note("folder-uid", &folder_key, "Wi-Fi password", "house-wifi-123").unwrap();
+        assert!(!record_uid.is_empty());
+        assert!(seen_data.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn reencrypt_record_for_wraps_the_record_key_for_the_destination_owner() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let dest_private_key = crypto::generate_private_key_ecc().unwrap();
+        let dest_public_key = crypto::public_key_ecc(&dest_private_key);
+
+        let record = Record {
+            uid: "source-uid".to_string(),
+            title: "Migrated Login".to_string(),
+            record_type: "login".to_string(),
+            folder_uid: Some("source-folder-uid".to_string()),
+            fields: vec![RecordField::new("login", vec![json!("alice")])],
+            ..Record::default()
+        };
+
+        let payload = sm.reencrypt_record_for(&record, &dest_public_key).unwrap();
+        assert!(!payload.record_uid.is_empty());
+        assert_ne!(payload.record_uid, record.uid);
+        assert_eq!(payload.folder_uid, "source-folder-uid");
+        assert!(payload.folder_key.is_empty());
+
+        let encrypted_record_key = base64_to_bytes(&payload.record_key).unwrap();
+        let record_key = crypto::private_decrypt(&encrypted_record_key, &dest_private_key).unwrap();
+        let encrypted_data = base64_to_bytes(&payload.data).unwrap();
+        let decrypted_json = crypto::decrypt_aes_gcm(&record_key, &encrypted_data).unwrap();
+        let decrypted: Record = serde_json::from_slice(&decrypted_json).unwrap();
+        assert_eq!(decrypted.title, "Migrated Login");
+        assert_eq!(decrypted.record_type, "login");
+
+        // The source record's own uid/folder_uid/revision describe the *source*
+        // vault's bookkeeping, not the secret itself, and must not leak into the
+        // destination application's encrypted data.
+        let decrypted_value: serde_json::Value = serde_json::from_slice(&decrypted_json).unwrap();
+        assert!(decrypted_value.get("uid").is_none());
+        assert!(decrypted_value.get("folder_uid").is_none());
+        assert!(decrypted_value.get("revision").is_none());
+    }
+
+    #[test]
+    fn note_body_recovers_the_plaintext_note_from_a_decrypted_record() {
+        let mut record = Record::default();
+        record.fields.push(RecordField::new("note", vec![json!("remember the milk")]));
+
+        assert_eq!(record.note_body(), Some("remember the milk".to_string()));
+    }
+
+    #[test]
+    fn note_body_returns_none_when_the_record_has_no_note_field() {
+        let record = Record::default();
+        assert_eq!(record.note_body(), None);
+    }
+
+    #[test]
+    fn set_server_public_key_id_persists_a_known_key_id() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let config = options.config.clone();
+        let sm = SecretsManager::new(options).unwrap();
+
+        sm.set_server_public_key_id("10").unwrap();
+        assert_eq!(config.get(ConfigKey::ServerPublicKeyId), Some("10".to_string()));
+    }
+
+    #[test]
+    fn set_server_public_key_id_rejects_an_unknown_key_id() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let config = options.config.clone();
+        let sm = SecretsManager::new(options).unwrap();
+        let before = config.get(ConfigKey::ServerPublicKeyId);
+
+        let err = sm.set_server_public_key_id("not-a-real-key-id").unwrap_err();
+        assert!(matches!(err, KSMRError::Config(_)));
+        assert_eq!(config.get(ConfigKey::ServerPublicKeyId), before);
+    }
+
+    #[test]
+    fn is_bound_reflects_whether_the_config_has_an_app_key() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let config = options.config.clone();
+        let sm = SecretsManager::new(options).unwrap();
+        assert!(!sm.is_bound());
+
+        config.set(ConfigKey::AppKey, bytes_to_base64(&crypto::generate_encryption_key_bytes()));
+        assert!(sm.is_bound());
+    }
+
+    #[test]
+    fn new_rejects_a_corrupt_stored_private_key_with_a_clear_diagnosis() {
+        let config = Arc::new(InMemoryKeyValueStorage::new());
+        config.set(ConfigKey::ClientId, "test-client-id".to_string());
+        config.set(ConfigKey::PrivateKey, "not valid base64 or DER!!".to_string());
+        let options = ClientOptions { config, ..ClientOptions::default() };
+
+        match SecretsManager::new(options) {
+            Err(KSMRError::Config(message)) => {
+                assert!(message.contains("corrupt"));
+                assert!(message.contains("repair_config"));
+            }
+            other => panic!("expected a Config error, got {}", other.is_ok()),
+        }
+    }
+
+    #[test]
+    fn repair_config_regenerates_the_key_pair_of_an_unbound_client() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let config = options.config.clone();
+        let sm = SecretsManager::new(options).unwrap();
+        let original_key = config.get(ConfigKey::PrivateKey).unwrap();
+
+        sm.repair_config().unwrap();
+
+        let repaired_key = config.get(ConfigKey::PrivateKey).unwrap();
+        assert_ne!(original_key, repaired_key);
+        assert!(sm.validate_private_key_if_present().is_ok());
+    }
+
+    #[test]
+    fn repair_config_refuses_to_touch_an_already_bound_client() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let config = options.config.clone();
+        config.set(ConfigKey::AppKey, bytes_to_base64(&crypto::generate_encryption_key_bytes()));
+        let sm = SecretsManager::new(options).unwrap();
+
+        assert!(matches!(sm.repair_config(), Err(KSMRError::Config(_))));
+    }
+
+    #[test]
+    fn decrypt_folder_key_unwraps_a_key_encrypted_under_the_app_key() {
+        let sm = SecretsManager::new(bound_options(Arc::new(|_, _, _| {
+            Ok(KsmHttpResponse { status_code: 200, data: vec![] })
+        })))
+        .unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let folder_key = crypto::generate_encryption_key_bytes();
+        let encrypted_folder_key = crypto::encrypt_aes_gcm(&app_key, &folder_key).unwrap();
+
+        let decrypted = sm.decrypt_folder_key(&app_key, &encrypted_folder_key).unwrap();
+        assert_eq!(decrypted, folder_key);
+    }
+
+    #[test]
+    fn decrypt_folder_key_rejects_the_wrong_wrapping_key() {
+        let sm = SecretsManager::new(bound_options(Arc::new(|_, _, _| {
+            Ok(KsmHttpResponse { status_code: 200, data: vec![] })
+        })))
+        .unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let wrong_key = crypto::generate_encryption_key_bytes();
+        let folder_key = crypto::generate_encryption_key_bytes();
+        let encrypted_folder_key = crypto::encrypt_aes_gcm(&app_key, &folder_key).unwrap();
+
+        assert!(sm.decrypt_folder_key(&wrong_key, &encrypted_folder_key).is_err());
+    }
+
+    #[test]
+    fn decrypt_folder_name_recovers_the_plaintext_name() {
+        let sm = SecretsManager::new(bound_options(Arc::new(|_, _, _| {
+            Ok(KsmHttpResponse { status_code: 200, data: vec![] })
+        })))
+        .unwrap();
+
+        let folder_key = crypto::generate_encryption_key_bytes();
+        let encrypted_name = crypto::encrypt_aes_gcm(&folder_key, b"Shared Secrets").unwrap();
+
+        let name = sm.decrypt_folder_name(&folder_key, &encrypted_name).unwrap();
+        assert_eq!(name, "Shared Secrets");
+    }
+
+    #[test]
+    fn decrypt_folder_composes_the_key_and_name_helpers() {
+        let sm = SecretsManager::new(bound_options(Arc::new(|_, _, _| {
+            Ok(KsmHttpResponse { status_code: 200, data: vec![] })
+        })))
+        .unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let folder_key = crypto::generate_encryption_key_bytes();
+        let encrypted_folder_key = crypto::encrypt_aes_gcm(&app_key, &folder_key).unwrap();
+        let encrypted_name = crypto::encrypt_aes_gcm(&folder_key, b"Engineering").unwrap();
+
+        let folder = sm.decrypt_folder(&app_key, &encrypted_folder_key, &encrypted_name).unwrap();
+        assert_eq!(folder.name, "Engineering");
+        assert_eq!(folder.folder_key_bytes, folder_key);
+    }
+
+    #[test]
+    fn parse_token_resolves_a_known_region_prefix() {
+        let (hostname, body) = SecretsManager::parse_token("US:ONE_TIME_TOKEN").unwrap();
+        assert_eq!(hostname, Some("keepersecurity.com".to_string()));
+        assert_eq!(body, "ONE_TIME_TOKEN");
+    }
+
+    #[test]
+    fn parse_token_rejects_an_unknown_region_prefix() {
+        assert!(SecretsManager::parse_token("XX:ONE_TIME_TOKEN").is_err());
+    }
+
+    #[test]
+    fn parse_token_treats_a_bare_token_as_having_no_region() {
+        let (hostname, body) = SecretsManager::parse_token("ONE_TIME_TOKEN").unwrap();
+        assert_eq!(hostname, None);
+        assert_eq!(body, "ONE_TIME_TOKEN");
+    }
+
+    #[test]
+    fn bind_if_needed_honors_a_region_prefixed_token_when_no_hostname_was_given() {
+        let config = Arc::new(InMemoryKeyValueStorage::new());
+        let options = ClientOptions {
+            token: Some(format!("EU:{}", bytes_to_url_safe_str(b"some-one-time-token"))),
+            config,
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+        assert_eq!(sm.hostname().unwrap(), "keepersecurity.eu");
+    }
+
+    #[test]
+    fn bind_if_needed_accepts_a_region_prefixed_token_that_agrees_with_the_configured_hostname() {
+        let config = Arc::new(InMemoryKeyValueStorage::new());
+        let options = ClientOptions {
+            token: Some(format!("EU:{}", bytes_to_url_safe_str(b"some-one-time-token"))),
+            hostname: Some("keepersecurity.eu".to_string()),
+            config,
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+        assert_eq!(sm.hostname().unwrap(), "keepersecurity.eu");
+    }
+
+    #[test]
+    fn bind_if_needed_rejects_a_region_prefixed_token_that_disagrees_with_the_configured_hostname() {
+        let config = Arc::new(InMemoryKeyValueStorage::new());
+        let options = ClientOptions {
+            token: Some(format!("EU:{}", bytes_to_url_safe_str(b"some-one-time-token"))),
+            hostname: Some("keepersecurity.com".to_string()),
+            config,
+            ..ClientOptions::default()
+        };
+        match SecretsManager::new(options) {
+            Err(KSMRError::Config(message)) => {
+                assert!(message.contains("keepersecurity.eu"));
+                assert!(message.contains("keepersecurity.com"));
+            }
+            other => panic!("expected a Config error, got {}", other.is_ok()),
+        }
+    }
+
+    #[test]
+    fn parse_http_date_matches_a_known_instant() {
+        let parsed = parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        assert_eq!(parsed.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs(), 784_111_777);
+    }
+
+    #[test]
+    fn parse_http_date_rejects_malformed_input() {
+        assert!(parse_http_date("not a date").is_err());
+    }
+
+    #[test]
+    fn check_clock_skew_reports_a_large_skew_against_a_stale_server_clock() {
+        let mut options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        options.server_date_override = Some(Arc::new(|| Ok("Sun, 06 Nov 1994 08:49:37 GMT".to_string())));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let skew = sm.check_clock_skew().unwrap();
+        assert!(skew.as_secs() > 365 * 24 * 3600);
+    }
+
+    #[test]
+    fn check_clock_skew_propagates_a_malformed_server_date() {
+        let mut options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        options.server_date_override = Some(Arc::new(|| Ok("garbage".to_string())));
+        let sm = SecretsManager::new(options).unwrap();
+
+        assert!(sm.check_clock_skew().is_err());
+    }
+
+    /// Guards tests that mutate `KSM_SKIP_VERIFY`, since `cargo test` runs
+    /// tests in parallel within the same process and the env var is global.
+    static SKIP_VERIFY_ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn require_secure_tls_rejects_a_client_with_verification_disabled() {
+        let _guard = SKIP_VERIFY_ENV_LOCK.lock().unwrap();
+        std::env::remove_var("KSM_SKIP_VERIFY");
+
+        let mut options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        options.verify_ssl_certs = false;
+        options = options.require_secure_tls();
+
+        assert!(SecretsManager::new(options).is_err());
+    }
+
+    #[test]
+    fn require_secure_tls_rejects_a_client_when_ksm_skip_verify_is_set() {
+        let _guard = SKIP_VERIFY_ENV_LOCK.lock().unwrap();
+        std::env::set_var("KSM_SKIP_VERIFY", "true");
+
+        let options =
+            bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })))
+                .require_secure_tls();
+        let result = SecretsManager::new(options);
+
+        std::env::remove_var("KSM_SKIP_VERIFY");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn require_secure_tls_allows_a_client_with_verification_enabled() {
+        let _guard = SKIP_VERIFY_ENV_LOCK.lock().unwrap();
+        std::env::remove_var("KSM_SKIP_VERIFY");
+
+        let options =
+            bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })))
+                .require_secure_tls();
+
+        assert!(SecretsManager::new(options).is_ok());
+    }
+
+    #[test]
+    fn default_client_options_do_not_enforce_secure_tls() {
+        let _guard = SKIP_VERIFY_ENV_LOCK.lock().unwrap();
+        std::env::set_var("KSM_SKIP_VERIFY", "true");
+
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let result = SecretsManager::new(options);
+
+        std::env::remove_var("KSM_SKIP_VERIFY");
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn ssl_verification_enabled_reflects_verify_ssl_certs_and_ksm_skip_verify() {
+        let _guard = SKIP_VERIFY_ENV_LOCK.lock().unwrap();
+        std::env::remove_var("KSM_SKIP_VERIFY");
+
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let sm = SecretsManager::new(options).unwrap();
+        assert!(sm.ssl_verification_enabled());
+
+        let mut disabled_options =
+            bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        disabled_options.verify_ssl_certs = false;
+        let sm = SecretsManager::new(disabled_options).unwrap();
+        assert!(!sm.ssl_verification_enabled());
+
+        std::env::set_var("KSM_SKIP_VERIFY", "true");
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let sm = SecretsManager::new(options).unwrap();
+        let enabled = sm.ssl_verification_enabled();
+        std::env::remove_var("KSM_SKIP_VERIFY");
+        assert!(!enabled);
+    }
+
+    #[test]
+    fn with_ssl_verification_overrides_only_for_the_duration_of_the_call() {
+        let _guard = SKIP_VERIFY_ENV_LOCK.lock().unwrap();
+        std::env::remove_var("KSM_SKIP_VERIFY");
+
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let sm = SecretsManager::new(options).unwrap();
+        assert!(sm.ssl_verification_enabled());
+
+        let observed_inside = sm.with_ssl_verification(false, || Ok(sm.ssl_verification_enabled())).unwrap();
+        assert!(!observed_inside);
+        assert!(sm.ssl_verification_enabled());
+    }
+
+    #[test]
+    fn with_ssl_verification_refuses_to_disable_verification_when_secure_tls_is_required() {
+        let mut options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        options.enforce_secure_tls = true;
+        let sm = SecretsManager::new(options).unwrap();
+
+        let result = sm.with_ssl_verification(false, || Ok(()));
+        assert!(matches!(result, Err(KSMRError::Config(_))));
+    }
+
+    #[test]
+    fn filtered_extra_headers_passes_through_caller_headers_but_drops_content_type() {
+        let mut extra_headers = std::collections::HashMap::new();
+        extra_headers.insert("X-Api-Gateway-Key".to_string(), "secret-value".to_string());
+        extra_headers.insert("content-type".to_string(), "text/plain".to_string());
+
+        let mut filtered = SecretsManager::filtered_extra_headers(&extra_headers);
+        filtered.sort();
+        assert_eq!(filtered, vec![("X-Api-Gateway-Key", "secret-value")]);
+    }
+
+    #[test]
+    fn set_log_level_updates_the_level_reported_by_log_level() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let sm = SecretsManager::new(options).unwrap();
+        assert_eq!(sm.log_level(), LogLevel::Info);
+
+        sm.set_log_level(LogLevel::Debug);
+        assert_eq!(sm.log_level(), LogLevel::Debug);
+
+        sm.set_log_level(LogLevel::Off);
+        assert_eq!(sm.log_level(), LogLevel::Off);
+    }
+
+    #[test]
+    fn client_options_log_level_seeds_the_clients_initial_level() {
+        let mut options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        options.log_level = LogLevel::Trace;
+        let sm = SecretsManager::new(options).unwrap();
+        assert_eq!(sm.log_level(), LogLevel::Trace);
+    }
+
+    #[test]
+    fn post_query_invokes_custom_transport() {
+        let options = bound_options(Arc::new(|url, _, _| {
+            assert!(url.ends_with("/update_secret"));
+            Ok(KsmHttpResponse { status_code: 200, data: vec![] })
+        }));
+        let sm = SecretsManager::new(options).unwrap();
+        let payload = sm.prepare_get_payload(None).unwrap();
+        let response = sm.post_query("update_secret", &payload).unwrap();
+        assert_eq!(response, Vec::<u8>::new());
+    }
+
+    #[test]
+    fn post_query_reports_metrics_after_each_call() {
+        let mut options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        options.metrics_callback = Some(Arc::new(move |metrics| seen_clone.lock().unwrap().push(metrics)));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let payload = sm.prepare_get_payload(None).unwrap();
+        sm.post_query("update_secret", &payload).unwrap();
+
+        let recorded = seen.lock().unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].path, "update_secret");
+        assert_eq!(recorded[0].status, Some(200));
+        assert!(!recorded[0].retried);
+        assert!(!recorded[0].cache_hit);
+    }
+
+    #[test]
+    fn post_query_reports_an_audit_event_with_no_body_or_headers() {
+        let mut options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        options.audit_callback = Some(Arc::new(move |event| seen_clone.lock().unwrap().push(event)));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let payload = sm.prepare_get_payload(None).unwrap();
+        sm.post_query("update_secret", &payload).unwrap();
+
+        let recorded = seen.lock().unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].path, "update_secret");
+        assert!(recorded[0].success);
+        assert_eq!(recorded[0].status, Some(200));
+        assert!(recorded[0].timestamp_millis > 0);
+    }
+
+    #[test]
+    fn post_query_reports_a_failed_audit_event_on_a_non_200_response() {
+        let mut options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 500, data: vec![] })));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        options.audit_callback = Some(Arc::new(move |event| seen_clone.lock().unwrap().push(event)));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let payload = sm.prepare_get_payload(None).unwrap();
+        assert!(sm.post_query("update_secret", &payload).is_err());
+
+        let recorded = seen.lock().unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert!(!recorded[0].success);
+        assert_eq!(recorded[0].status, Some(500));
+    }
+
+    #[test]
+    fn post_query_errors_when_get_secret_returns_an_empty_body() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let sm = SecretsManager::new(options).unwrap();
+        let payload = sm.prepare_get_payload(None).unwrap();
+        assert!(sm.post_query("get_secret", &payload).is_err());
+    }
+
+    #[test]
+    fn post_query_reports_invalid_client_version_with_the_servers_hint() {
+        let options = bound_options(Arc::new(|_, _, _| {
+            let body = serde_json::json!({
+                "error": "invalid_client_version",
+                "additional_info": "Client version KSM_RS was deprecated; upgrade to the latest release",
+            });
+            Ok(KsmHttpResponse { status_code: 400, data: body.to_string().into_bytes() })
+        }));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let err = sm.post_query("update_secret", &serde_json::json!({})).unwrap_err();
+        match err {
+            KSMRError::ClientVersion(info) => assert!(info.contains("deprecated")),
+            other => panic!("expected KSMRError::ClientVersion, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn post_query_falls_back_to_a_generic_network_error_for_other_failures() {
+        let options = bound_options(Arc::new(|_, _, _| {
+            Ok(KsmHttpResponse { status_code: 500, data: b"internal server error".to_vec() })
+        }));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let err = sm.post_query("update_secret", &serde_json::json!({})).unwrap_err();
+        assert!(matches!(err, KSMRError::Network(_)));
+    }
+
+    /// Bound (no-token) client backed by a fake gateway that answers
+    /// `get_secret` with a single record whose wire JSON is `extra_wire_fields`
+    /// merged in (e.g. `{"isEditable": false}`).
+    fn can_write_test_options(record_uid: &str, extra_wire_fields: serde_json::Value) -> ClientOptions {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let app_key = crypto::generate_encryption_key_bytes();
+        let record_json = serde_json::json!({"title": "Shared", "type": "login", "fields": [], "custom": []});
+        let blob = crypto::encrypt_aes_gcm(&app_key, record_json.to_string().as_bytes()).unwrap();
+
+        let record_uid = record_uid.to_string();
+        let options = bound_options(Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let mut wire_record = serde_json::json!({"recordUid": record_uid, "data": bytes_to_base64(&blob)});
+            for (key, value) in extra_wire_fields.as_object().unwrap() {
+                wire_record[key] = value.clone();
+            }
+            let response_json = serde_json::json!({"records": [wire_record]});
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+        options
+    }
+
+    #[test]
+    fn can_write_is_true_when_the_gateway_reports_the_share_is_editable() {
+        let options = can_write_test_options("record-uid", serde_json::json!({"isEditable": true}));
+        let sm = SecretsManager::new(options).unwrap();
+        assert!(sm.can_write("record-uid").unwrap());
+    }
+
+    #[test]
+    fn can_write_is_false_for_a_read_only_share() {
+        let options = can_write_test_options("record-uid", serde_json::json!({"isEditable": false}));
+        let sm = SecretsManager::new(options).unwrap();
+        assert!(!sm.can_write("record-uid").unwrap());
+    }
+
+    #[test]
+    fn can_write_defaults_to_true_when_the_gateway_omits_is_editable() {
+        let options = can_write_test_options("record-uid", serde_json::json!({}));
+        let sm = SecretsManager::new(options).unwrap();
+        assert!(sm.can_write("record-uid").unwrap());
+    }
+
+    #[test]
+    fn disaster_recovery_cache_serves_the_last_response_when_the_network_fails() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let uid = crate::utils::generate_uid();
+        let record_json = serde_json::json!({"title": "Cached", "type": "login", "fields": [], "custom": []});
+        let blob = crypto::encrypt_aes_gcm(&app_key, record_json.to_string().as_bytes()).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let uid_resp = uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let network_is_down = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let network_is_down_clone = network_is_down.clone();
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            if network_is_down_clone.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(KSMRError::Network("connection refused".into()));
+            }
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let mut response_json = serde_json::json!({
+                "records": [{"recordUid": uid_resp, "data": bytes_to_base64(&blob)}],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            enable_disaster_recovery_cache: true,
+            metrics_callback: Some(Arc::new(move |m| seen_clone.lock().unwrap().push(m))),
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        let records = sm.get_secrets(None).unwrap();
+        assert_eq!(records[0].title, "Cached");
+
+        network_is_down.store(true, std::sync::atomic::Ordering::SeqCst);
+        let records_during_outage = sm.get_secrets(None).unwrap();
+        assert_eq!(records_during_outage[0].title, "Cached");
+
+        let recorded = seen.lock().unwrap();
+        assert!(!recorded[0].cache_hit);
+        assert!(recorded.last().unwrap().cache_hit);
+    }
+
+    #[test]
+    fn get_secrets_picks_up_a_server_side_app_key_rotation_without_a_restart() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key_v1 = crypto::generate_encryption_key_bytes();
+        let app_key_v2 = crypto::generate_encryption_key_bytes();
+        let uid = crate::utils::generate_uid();
+        let record_v1 = serde_json::json!({"title": "Before Rotation", "type": "login", "fields": [], "custom": []});
+        let record_v2 = serde_json::json!({"title": "After Rotation", "type": "login", "fields": [], "custom": []});
+        let blob_v1 = crypto::encrypt_aes_gcm(&app_key_v1, record_v1.to_string().as_bytes()).unwrap();
+        let blob_v2 = crypto::encrypt_aes_gcm(&app_key_v2, record_v2.to_string().as_bytes()).unwrap();
+        let wrapped_v1 = crypto::encrypt_aes_gcm(&token_bytes, &app_key_v1).unwrap();
+        let wrapped_v2 = crypto::encrypt_aes_gcm(&token_bytes, &app_key_v2).unwrap();
+
+        let uid_resp = uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let rotated = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let rotation_sent = std::sync::atomic::AtomicBool::new(false);
+        let rotated_for_closure = rotated.clone();
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let is_rotated = rotated_for_closure.load(std::sync::atomic::Ordering::SeqCst);
+            let blob = if is_rotated { &blob_v2 } else { &blob_v1 };
+            let mut response_json = serde_json::json!({
+                "records": [{"recordUid": uid_resp, "data": bytes_to_base64(blob)}],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&wrapped_v1));
+            } else if is_rotated && !rotation_sent.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&wrapped_v2));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options =
+            ClientOptions { token: Some(token), hostname: Some("local.test".to_string()), custom_post_function: Some(custom), ..ClientOptions::default() };
+        let sm = SecretsManager::new(options).unwrap();
+
+        let records = sm.get_secrets(None).unwrap();
+        assert_eq!(records[0].title, "Before Rotation");
+
+        rotated.store(true, std::sync::atomic::Ordering::SeqCst);
+        let records_after_rotation = sm.get_secrets(None).unwrap();
+        assert_eq!(records_after_rotation[0].title, "After Rotation");
+    }
+
+    #[test]
+    fn warm_cache_rejects_when_disaster_recovery_cache_is_disabled() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let sm = SecretsManager::new(options).unwrap();
+        assert!(matches!(sm.warm_cache(), Err(KSMRError::Config(_))));
+    }
+
+    #[test]
+    fn warm_cache_populates_the_cache_so_a_later_outage_is_served_from_it() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let uid = crate::utils::generate_uid();
+        let record_json = serde_json::json!({"title": "Warmed", "type": "login", "fields": [], "custom": []});
+        let blob = crypto::encrypt_aes_gcm(&app_key, record_json.to_string().as_bytes()).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let uid_resp = uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let network_is_down = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let network_is_down_clone = network_is_down.clone();
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            if network_is_down_clone.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(KSMRError::Network("connection refused".into()));
+            }
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let mut response_json = serde_json::json!({
+                "records": [{"recordUid": uid_resp, "data": bytes_to_base64(&blob)}],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            enable_disaster_recovery_cache: true,
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        sm.warm_cache().unwrap();
+
+        network_is_down.store(true, std::sync::atomic::Ordering::SeqCst);
+        let records_during_outage = sm.get_secrets(None).unwrap();
+        assert_eq!(records_during_outage[0].title, "Warmed");
+    }
+
+    #[test]
+    fn inspect_cache_rejects_when_nothing_has_been_cached_yet() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let sm = SecretsManager::new(options).unwrap();
+        assert!(matches!(sm.inspect_cache(), Err(KSMRError::Config(_))));
+    }
+
+    #[test]
+    fn inspect_cache_decrypts_the_cached_response_without_any_network_call() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let uid = crate::utils::generate_uid();
+        let record_json = serde_json::json!({"title": "Warmed", "type": "login", "fields": [], "custom": []});
+        let blob = crypto::encrypt_aes_gcm(&app_key, record_json.to_string().as_bytes()).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let uid_resp = uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            calls_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let mut response_json = serde_json::json!({
+                "records": [{"recordUid": uid_resp, "data": bytes_to_base64(&blob)}],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            enable_disaster_recovery_cache: true,
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        sm.warm_cache().unwrap();
+        let calls_after_warm = calls.load(std::sync::atomic::Ordering::SeqCst);
+
+        let inspected = sm.inspect_cache().unwrap();
+        assert_eq!(inspected.records[0].title, "Warmed");
+        assert_eq!(inspected.source, crate::dto::ResponseSource::Cache);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), calls_after_warm);
+    }
+
+    #[test]
+    fn spawn_cache_refresher_warms_the_cache_repeatedly_on_its_own() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let uid = crate::utils::generate_uid();
+        let record_json = serde_json::json!({"title": "Warmed", "type": "login", "fields": [], "custom": []});
+        let blob = crypto::encrypt_aes_gcm(&app_key, record_json.to_string().as_bytes()).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let uid_resp = uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            calls_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let mut response_json = serde_json::json!({
+                "records": [{"recordUid": uid_resp, "data": bytes_to_base64(&blob)}],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            enable_disaster_recovery_cache: true,
+            ..ClientOptions::default()
+        };
+        let sm = Arc::new(SecretsManager::new(options).unwrap());
+
+        let _handle = sm.spawn_cache_refresher(Duration::from_millis(10));
+        std::thread::sleep(Duration::from_millis(100));
+
+        assert!(calls.load(std::sync::atomic::Ordering::SeqCst) >= 2);
+    }
+
+    #[test]
+    fn clear_cache_drops_the_cached_response_so_a_later_outage_fails_outright() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let uid = crate::utils::generate_uid();
+        let record_json = serde_json::json!({"title": "Cached", "type": "login", "fields": [], "custom": []});
+        let blob = crypto::encrypt_aes_gcm(&app_key, record_json.to_string().as_bytes()).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let uid_resp = uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let network_is_down = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let network_is_down_clone = network_is_down.clone();
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            if network_is_down_clone.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(KSMRError::Network("connection refused".into()));
+            }
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let mut response_json = serde_json::json!({
+                "records": [{"recordUid": uid_resp, "data": bytes_to_base64(&blob)}],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            enable_disaster_recovery_cache: true,
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        let records = sm.get_secrets(None).unwrap();
+        assert_eq!(records[0].title, "Cached");
+
+        sm.clear_cache();
+
+        network_is_down.store(true, std::sync::atomic::Ordering::SeqCst);
+        let result = sm.get_secrets(None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn fetch_and_decrypt_secrets_reports_whether_the_response_came_from_the_cache() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let uid = crate::utils::generate_uid();
+        let record_json = serde_json::json!({"title": "Cached", "type": "login", "fields": [], "custom": []});
+        let blob = crypto::encrypt_aes_gcm(&app_key, record_json.to_string().as_bytes()).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let uid_resp = uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let network_is_down = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let network_is_down_clone = network_is_down.clone();
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            if network_is_down_clone.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(KSMRError::Network("connection refused".into()));
+            }
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let mut response_json = serde_json::json!({
+                "records": [{"recordUid": uid_resp, "data": bytes_to_base64(&blob)}],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            enable_disaster_recovery_cache: true,
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        // Binding takes an extra round trip (see `get_secrets`'s `just_bound`
+        // retry), so warm the client up through that before asserting on a
+        // single `fetch_and_decrypt_secrets` call's source below.
+        sm.get_secrets(None).unwrap();
+
+        let live = sm.fetch_and_decrypt_secrets(None).unwrap();
+        assert_eq!(live.source, crate::dto::ResponseSource::Live);
+
+        network_is_down.store(true, std::sync::atomic::Ordering::SeqCst);
+        let cached = sm.fetch_and_decrypt_secrets(None).unwrap();
+        assert_eq!(cached.source, crate::dto::ResponseSource::Cache);
+    }
+
+    #[test]
+    fn disaster_recovery_cache_does_not_mask_network_failures_when_disabled() {
+        let custom: Arc<CustomPostFn> =
+            Arc::new(|_url, _body, _verify| Err(KSMRError::Network("connection refused".into())));
+        let options = bound_options(custom);
+        let sm = SecretsManager::new(options).unwrap();
+
+        assert!(sm.get_secrets(None).is_err());
+    }
+
+    #[test]
+    fn save_updates_a_record_with_its_own_key() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let mut record = Record { uid: "uid1".to_string(), ..Default::default() };
+        record.record_key_bytes = crypto::generate_encryption_key_bytes();
+        record.fields.push(RecordField::new("login", vec![json!("alice")]));
+
+        assert!(sm.save(&record).is_ok());
+    }
+
+    #[test]
+    fn save_all_reports_partial_failures_without_aborting_the_batch() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let mut ok_record = Record { uid: "uid-ok".to_string(), ..Default::default() };
+        ok_record.record_key_bytes = crypto::generate_encryption_key_bytes();
+        let missing_key_record = Record { uid: "uid-missing-key".to_string(), ..Default::default() };
+
+        let results = sm.save_all(&[ok_record, missing_key_record], None, None);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].uid, "uid-ok");
+        assert!(results[0].error.is_none());
+        assert_eq!(results[1].uid, "uid-missing-key");
+        assert!(results[1].error.is_some());
+    }
+
+    #[test]
+    fn save_all_stops_issuing_requests_once_cancelled() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let options = bound_options(Arc::new(move |_, _, _| {
+            calls_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(KsmHttpResponse { status_code: 200, data: vec![] })
+        }));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let make_record = |uid: &str| {
+            let mut record = Record { uid: uid.to_string(), ..Default::default() };
+            record.record_key_bytes = crypto::generate_encryption_key_bytes();
+            record
+        };
+        let records = vec![make_record("uid-1"), make_record("uid-2"), make_record("uid-3")];
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let results = sm.save_all(&records, None, Some(&token));
+
+        assert!(results.is_empty());
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn cancellation_token_clones_share_the_same_flag() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        token.cancel();
+        assert!(clone.is_cancelled());
+    }
+
+    /// Bound (no-token) client backed by a fake gateway that answers
+    /// `get_secret` with a single record (one `login` field, value `alice`)
+    /// and `update_secret` with a bare 200, for exercising
+    /// [`SecretsManager::update_field_value`]'s fetch/mutate/save sequence.
+    fn update_field_value_test_options(record_uid: &str) -> ClientOptions {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let app_key = crypto::generate_encryption_key_bytes();
+        let record_json = serde_json::json!({
+            "title": "Shared",
+            "type": "login",
+            "fields": [{"type": "login", "value": ["alice"]}],
+            "custom": [],
+        });
+        let blob = crypto::encrypt_aes_gcm(&app_key, record_json.to_string().as_bytes()).unwrap();
+
+        let record_uid = record_uid.to_string();
+        let options = bound_options(Arc::new(move |url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            if url.ends_with("update_secret") {
+                return Ok(KsmHttpResponse { status_code: 200, data: vec![] });
+            }
+            let response_json = serde_json::json!({
+                "records": [{"recordUid": record_uid, "data": bytes_to_base64(&blob)}],
+            });
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+        options
+    }
+
+    #[test]
+    fn update_field_value_fetches_mutates_and_saves_by_type() {
+        let options = update_field_value_test_options("record-uid");
+        let sm = SecretsManager::new(options).unwrap();
+
+        sm.update_field_value(
+            "record-uid",
+            FieldSelector::Type("login".to_string()),
+            json!("bob"),
+            None,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn update_field_value_errors_when_no_field_matches_the_selector() {
+        let options = update_field_value_test_options("record-uid");
+        let sm = SecretsManager::new(options).unwrap();
+
+        let err = sm
+            .update_field_value("record-uid", FieldSelector::Type("password".to_string()), json!("bob"), None)
+            .unwrap_err();
+        assert!(matches!(err, KSMRError::RecordNotFound(_)));
+    }
+
+    #[test]
+    fn update_field_value_errors_when_the_record_does_not_exist() {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let options = bound_options(Arc::new(move |_, body, _| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let response_json = serde_json::json!({"records": []});
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&crypto::generate_encryption_key_bytes()));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let err = sm
+            .update_field_value("missing-uid", FieldSelector::Type("login".to_string()), json!("bob"), None)
+            .unwrap_err();
+        assert!(matches!(err, KSMRError::RecordNotFound(_)));
+    }
+
+    #[test]
+    fn get_vault_snapshot_nests_records_and_child_folders_and_separates_unfiled_records() {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let app_key = crypto::generate_encryption_key_bytes();
+
+        let parent_uid = crate::utils::generate_uid();
+        let child_uid = crate::utils::generate_uid();
+        let parent_key = crypto::generate_encryption_key_bytes();
+        let child_key = crypto::generate_encryption_key_bytes();
+        let encrypted_parent_key = crypto::encrypt_aes_gcm(&app_key, &parent_key).unwrap();
+        let encrypted_child_key = crypto::encrypt_aes_gcm(&app_key, &child_key).unwrap();
+        let parent_name = crypto::encrypt_aes_gcm(
+            &parent_key,
+            serde_json::json!({"name": "Parent"}).to_string().as_bytes(),
+        )
+        .unwrap();
+        let child_name = crypto::encrypt_aes_gcm(
+            &child_key,
+            serde_json::json!({"name": "Child"}).to_string().as_bytes(),
+        )
+        .unwrap();
+
+        let parent_record_uid = crate::utils::generate_uid();
+        let parent_record_json = serde_json::json!({"title": "In Parent", "type": "login", "fields": [], "custom": []});
+        let parent_record_blob =
+            crypto::encrypt_aes_gcm(&parent_key, parent_record_json.to_string().as_bytes()).unwrap();
+
+        let unfiled_record_uid = crate::utils::generate_uid();
+        let unfiled_record_json = serde_json::json!({"title": "Unfiled", "type": "login", "fields": [], "custom": []});
+        let unfiled_record_blob =
+            crypto::encrypt_aes_gcm(&app_key, unfiled_record_json.to_string().as_bytes()).unwrap();
+
+        let parent_uid_resp = parent_uid.clone();
+        let child_uid_resp = child_uid.clone();
+        let parent_record_uid_resp = parent_record_uid.clone();
+        let unfiled_record_uid_resp = unfiled_record_uid.clone();
+        let options = bound_options(Arc::new(move |_, body, _| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let response_json = serde_json::json!({
+                "records": [{"recordUid": unfiled_record_uid_resp, "data": bytes_to_base64(&unfiled_record_blob)}],
+                "folders": [
+                    {
+                        "folderUid": parent_uid_resp,
+                        "folderKey": bytes_to_base64(&encrypted_parent_key),
+                        "data": bytes_to_base64(&parent_name),
+                        "records": [
+                            {"recordUid": parent_record_uid_resp, "data": bytes_to_base64(&parent_record_blob)},
+                        ],
+                    },
+                    {
+                        "folderUid": child_uid_resp,
+                        "parentUid": parent_uid_resp,
+                        "folderKey": bytes_to_base64(&encrypted_child_key),
+                        "data": bytes_to_base64(&child_name),
+                        "records": [],
+                    },
+                ],
+            });
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let snapshot = sm.get_vault_snapshot().unwrap();
+
+        assert_eq!(snapshot.unfiled_records.len(), 1);
+        assert_eq!(snapshot.unfiled_records[0].uid, unfiled_record_uid);
+
+        assert_eq!(snapshot.folders.len(), 1);
+        let parent_node = &snapshot.folders[0];
+        assert_eq!(parent_node.folder.folder_uid, parent_uid);
+        assert_eq!(parent_node.records.len(), 1);
+        assert_eq!(parent_node.records[0].uid, parent_record_uid);
+        assert_eq!(parent_node.children.len(), 1);
+        assert_eq!(parent_node.children[0].folder.folder_uid, child_uid);
+        assert!(parent_node.children[0].records.is_empty());
+    }
+
+    #[test]
+    fn get_vault_snapshot_nests_a_multi_level_orphan_subtree_instead_of_flattening_it() {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let app_key = crypto::generate_encryption_key_bytes();
+
+        // `orphan_a`'s own parent is never included in the share at all; `orphan_b`
+        // is filed under `orphan_a`. Both should end up in one subtree rooted at
+        // `orphan_a`, not as two disconnected top-level nodes.
+        let missing_parent_uid = crate::utils::generate_uid();
+        let orphan_a_uid = crate::utils::generate_uid();
+        let orphan_b_uid = crate::utils::generate_uid();
+        let orphan_a_key = crypto::generate_encryption_key_bytes();
+        let orphan_b_key = crypto::generate_encryption_key_bytes();
+        let encrypted_orphan_a_key = crypto::encrypt_aes_gcm(&app_key, &orphan_a_key).unwrap();
+        let encrypted_orphan_b_key = crypto::encrypt_aes_gcm(&app_key, &orphan_b_key).unwrap();
+        let orphan_a_name =
+            crypto::encrypt_aes_gcm(&orphan_a_key, serde_json::json!({"name": "Orphan A"}).to_string().as_bytes())
+                .unwrap();
+        let orphan_b_name =
+            crypto::encrypt_aes_gcm(&orphan_b_key, serde_json::json!({"name": "Orphan B"}).to_string().as_bytes())
+                .unwrap();
+
+        let missing_parent_uid_resp = missing_parent_uid.clone();
+        let orphan_a_uid_resp = orphan_a_uid.clone();
+        let orphan_b_uid_resp = orphan_b_uid.clone();
+        let options = bound_options(Arc::new(move |_, body, _| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let response_json = serde_json::json!({
+                "records": [],
+                "folders": [
+                    {
+                        "folderUid": orphan_a_uid_resp,
+                        "parentUid": missing_parent_uid_resp,
+                        "folderKey": bytes_to_base64(&encrypted_orphan_a_key),
+                        "data": bytes_to_base64(&orphan_a_name),
+                        "records": [],
+                    },
+                    {
+                        "folderUid": orphan_b_uid_resp,
+                        "parentUid": orphan_a_uid_resp,
+                        "folderKey": bytes_to_base64(&encrypted_orphan_b_key),
+                        "data": bytes_to_base64(&orphan_b_name),
+                        "records": [],
+                    },
+                ],
+            });
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let snapshot = sm.get_vault_snapshot().unwrap();
+
+        assert_eq!(snapshot.folders.len(), 1);
+        let orphan_a_node = &snapshot.folders[0];
+        assert_eq!(orphan_a_node.folder.folder_uid, orphan_a_uid);
+        assert_eq!(orphan_a_node.children.len(), 1);
+        assert_eq!(orphan_a_node.children[0].folder.folder_uid, orphan_b_uid);
+    }
+
+    #[test]
+    fn folder_summary_tallies_record_counts_per_folder() {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let app_key = crypto::generate_encryption_key_bytes();
+
+        let parent_uid = crate::utils::generate_uid();
+        let child_uid = crate::utils::generate_uid();
+        let parent_key = crypto::generate_encryption_key_bytes();
+        let child_key = crypto::generate_encryption_key_bytes();
+        let encrypted_parent_key = crypto::encrypt_aes_gcm(&app_key, &parent_key).unwrap();
+        let encrypted_child_key = crypto::encrypt_aes_gcm(&app_key, &child_key).unwrap();
+        let parent_name = crypto::encrypt_aes_gcm(
+            &parent_key,
+            serde_json::json!({"name": "Parent"}).to_string().as_bytes(),
+        )
+        .unwrap();
+        let child_name = crypto::encrypt_aes_gcm(
+            &child_key,
+            serde_json::json!({"name": "Child"}).to_string().as_bytes(),
+        )
+        .unwrap();
+
+        let parent_record_uid = crate::utils::generate_uid();
+        let parent_record_json = serde_json::json!({"title": "In Parent", "type": "login", "fields": [], "custom": []});
+        let parent_record_blob =
+            crypto::encrypt_aes_gcm(&parent_key, parent_record_json.to_string().as_bytes()).unwrap();
+
+        let parent_uid_resp = parent_uid.clone();
+        let child_uid_resp = child_uid.clone();
+        let parent_record_uid_resp = parent_record_uid.clone();
+        let options = bound_options(Arc::new(move |_, body, _| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let response_json = serde_json::json!({
+                "records": [],
+                "folders": [
+                    {
+                        "folderUid": parent_uid_resp,
+                        "folderKey": bytes_to_base64(&encrypted_parent_key),
+                        "data": bytes_to_base64(&parent_name),
+                        "records": [
+                            {"recordUid": parent_record_uid_resp, "data": bytes_to_base64(&parent_record_blob)},
+                        ],
+                    },
+                    {
+                        "folderUid": child_uid_resp,
+                        "parentUid": parent_uid_resp,
+                        "folderKey": bytes_to_base64(&encrypted_child_key),
+                        "data": bytes_to_base64(&child_name),
+                        "records": [],
+                    },
+                ],
+            });
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let mut summary = sm.folder_summary().unwrap();
+        summary.sort_by(|a, b| a.folder_uid.cmp(&b.folder_uid));
+
+        let parent_summary = summary.iter().find(|f| f.folder_uid == parent_uid).unwrap();
+        assert_eq!(parent_summary.name, "Parent");
+        assert_eq!(parent_summary.parent_uid, None);
+        assert_eq!(parent_summary.record_count, 1);
+
+        let child_summary = summary.iter().find(|f| f.folder_uid == child_uid).unwrap();
+        assert_eq!(child_summary.name, "Child");
+        assert_eq!(child_summary.parent_uid, Some(parent_uid.clone()));
+        assert_eq!(child_summary.record_count, 0);
+    }
+
+    #[cfg(feature = "unsafe-export-keys")]
+    #[test]
+    fn app_key_bytes_returns_the_stored_app_key() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let config = options.config.clone();
+        let sm = SecretsManager::new(options).unwrap();
+        assert!(sm.app_key_bytes().is_err());
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        config.set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+        assert_eq!(sm.app_key_bytes().unwrap().as_slice(), app_key.as_slice());
+    }
+
+    fn file_upload_test_options(
+        custom: Arc<CustomPostFn>,
+        file_upload: Arc<FileUploadFn>,
+        owner_public_key: &[u8],
+    ) -> ClientOptions {
+        let config = Arc::new(InMemoryKeyValueStorage::new());
+        config.set(ConfigKey::ClientId, "test-client-id".to_string());
+        config.set(
+            ConfigKey::PrivateKey,
+            bytes_to_base64(&crypto::generate_private_key_ecc().unwrap().to_bytes()),
+        );
+        config.set(ConfigKey::ServerPublicKeyId, DEFAULT_KEY_ID.to_string());
+        config.set(ConfigKey::Hostname, "local.test".to_string());
+        config.set(ConfigKey::OwnerPublicKey, bytes_to_base64(owner_public_key));
+        ClientOptions {
+            config,
+            custom_post_function: Some(custom),
+            file_upload_override: Some(file_upload),
+            ..ClientOptions::default()
+        }
+    }
+
+    /// Fake server that decrypts each request's transmission key and answers `add_file`
+    /// calls with a stub upload URL, leaving every other path's response body empty.
+    fn fake_file_server() -> Arc<CustomPostFn> {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        Arc::new(move |url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let response_json = if url.ends_with("add_file") {
+                serde_json::json!({"url": "https://upload.example/put", "parameters": "{\"key\":\"abc\"}"})
+            } else {
+                serde_json::json!({})
+            };
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        })
+    }
+
+    #[test]
+    fn create_secret_and_fetch_returns_the_created_record_ready_to_use() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let uid = crate::utils::generate_uid();
+        let record_json = serde_json::json!({"title": "Example", "type": "login", "fields": [], "custom": []});
+        let blob = crypto::encrypt_aes_gcm(&app_key, record_json.to_string().as_bytes()).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let uid_resp = uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let custom: Arc<CustomPostFn> = Arc::new(move |url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let response_json = if url.ends_with("create_secret") {
+                serde_json::json!({})
+            } else {
+                let mut response_json = serde_json::json!({
+                    "records": [{"recordUid": uid_resp, "data": bytes_to_base64(&blob)}],
+                });
+                if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                    response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+                }
+                response_json
+            };
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        let record = RecordCreate::new("login", "Example");
+        let created = sm.create_secret_and_fetch("folder-uid", &[1u8; 32], &record).unwrap();
+
+        assert_eq!(created.title, "Example");
+        assert!(!created.record_key_bytes.is_empty());
+    }
+
+    #[test]
+    fn create_secret_with_files_uploads_every_file_and_attaches_file_refs() {
+        let owner_key = crypto::generate_private_key_ecc().unwrap();
+        let uploaded = Arc::new(Mutex::new(Vec::new()));
+        let uploaded_for_closure = uploaded.clone();
+        let file_upload: Arc<FileUploadFn> = Arc::new(move |url, params, data| {
+            uploaded_for_closure.lock().unwrap().push((url.to_string(), params.clone(), data.to_vec()));
+            Ok(())
+        });
+        let options =
+            file_upload_test_options(fake_file_server(), file_upload, &crypto::public_key_ecc(&owner_key));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let record = RecordCreate::new("login", "Example");
+        let files = vec![
+            KeeperFileUpload::new("a.txt", "A", "text/plain", b"hello".to_vec()),
+            KeeperFileUpload::new("b.txt", "B", "text/plain", b"world".to_vec()),
+        ];
+        let record_uid = sm.create_secret_with_files("folder-uid", &[1u8; 32], &record, files).unwrap();
+
+        assert!(!record_uid.is_empty());
+        assert_eq!(uploaded.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn create_secret_with_files_reports_progress_on_partial_failure() {
+        let owner_key = crypto::generate_private_key_ecc().unwrap();
+        let call_count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let file_upload: Arc<FileUploadFn> = Arc::new(move |_url, _params, _data| {
+            if call_count.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 1 {
+                Err(KSMRError::Network("connection reset".into()))
+            } else {
+                Ok(())
+            }
+        });
+        let options =
+            file_upload_test_options(fake_file_server(), file_upload, &crypto::public_key_ecc(&owner_key));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let record = RecordCreate::new("login", "Example");
+        let files = vec![
+            KeeperFileUpload::new("a.txt", "A", "text/plain", b"hello".to_vec()),
+            KeeperFileUpload::new("b.txt", "B", "text/plain", b"world".to_vec()),
+        ];
+        let err = sm.create_secret_with_files("folder-uid", &[1u8; 32], &record, files).unwrap_err();
+
+        let message = err.to_string();
+        assert!(message.contains("1/2"), "unexpected error message: {message}");
+        assert!(message.contains("'b.txt'"), "unexpected error message: {message}");
+    }
+
+    #[test]
+    fn upload_file_retries_a_transient_failure_and_eventually_succeeds() {
+        let owner_key = crypto::generate_private_key_ecc().unwrap();
+        let attempts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let attempts_clone = attempts.clone();
+        let file_upload: Arc<FileUploadFn> = Arc::new(move |_url, _params, _data| {
+            if attempts_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < 2 {
+                Err(KSMRError::Network("connection reset".into()))
+            } else {
+                Ok(())
+            }
+        });
+        let mut options =
+            file_upload_test_options(fake_file_server(), file_upload, &crypto::public_key_ecc(&owner_key));
+        options.upload_retries = 2;
+        let sm = SecretsManager::new(options).unwrap();
+
+        let mut record = Record { uid: "owner-uid".to_string(), ..Record::default() };
+        record.record_key_bytes = crypto::generate_encryption_key_bytes();
+        let file = KeeperFileUpload::new("a.txt", "A", "text/plain", b"hello".to_vec());
+
+        assert!(sm.upload_file(&mut record, &file).is_ok());
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn upload_file_gives_up_after_exhausting_its_retries() {
+        let owner_key = crypto::generate_private_key_ecc().unwrap();
+        let attempts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let attempts_clone = attempts.clone();
+        let file_upload: Arc<FileUploadFn> = Arc::new(move |_url, _params, _data| {
+            attempts_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Err(KSMRError::Network("connection reset".into()))
+        });
+        let mut options =
+            file_upload_test_options(fake_file_server(), file_upload, &crypto::public_key_ecc(&owner_key));
+        options.upload_retries = 2;
+        let sm = SecretsManager::new(options).unwrap();
+
+        let mut record = Record { uid: "owner-uid".to_string(), ..Record::default() };
+        record.record_key_bytes = crypto::generate_encryption_key_bytes();
+        let file = KeeperFileUpload::new("a.txt", "A", "text/plain", b"hello".to_vec());
+
+        assert!(sm.upload_file(&mut record, &file).is_err());
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn verify_file_integrity_accepts_a_matching_size() {
+        let meta = FileRecordMeta { name: "a.txt".into(), size: 5, title: "A".into(), last_modified: 0, mime_type: "text/plain".into() };
+        assert!(SecretsManager::verify_file_integrity(&meta, b"hello").is_ok());
+    }
+
+    #[test]
+    fn verify_file_integrity_rejects_a_truncated_download() {
+        let meta = FileRecordMeta { name: "a.txt".into(), size: 5, title: "A".into(), last_modified: 0, mime_type: "text/plain".into() };
+        let err = SecretsManager::verify_file_integrity(&meta, b"hel").unwrap_err();
+        assert!(matches!(err, KSMRError::Crypto(_)));
+    }
+
+    #[test]
+    fn download_file_to_writer_decrypts_the_downloaded_bytes_into_the_writer() {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let app_key = crypto::generate_encryption_key_bytes();
+        let file_key = crypto::generate_encryption_key_bytes();
+        let file_uid = crate::utils::generate_uid();
+        let file_json = serde_json::json!({"title": "a.txt", "type": "file", "fields": [], "custom": []});
+        let file_blob = crypto::encrypt_aes_gcm(&file_key, file_json.to_string().as_bytes()).unwrap();
+        let wrapped_file_key = crypto::encrypt_aes_gcm(&app_key, &file_key).unwrap();
+        let plaintext = b"hello attachment".to_vec();
+        let encrypted_download = crypto::encrypt_aes_gcm(&file_key, &plaintext).unwrap();
+
+        let file_uid_resp = file_uid.clone();
+        let options = bound_options(Arc::new(move |url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let response_json = if url.ends_with("request_download") {
+                serde_json::json!({"url": "https://download.example/file"})
+            } else {
+                serde_json::json!({
+                    "records": [{
+                        "recordUid": file_uid_resp,
+                        "recordKey": bytes_to_base64(&wrapped_file_key),
+                        "data": bytes_to_base64(&file_blob),
+                    }],
+                })
+            };
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+        let download_override: Arc<FileDownloadFn> = Arc::new(move |_url| Ok(encrypted_download.clone()));
+        let options = ClientOptions { file_download_override: Some(download_override), ..options };
+        let sm = SecretsManager::new(options).unwrap();
+
+        let owner_record = Record {
+            uid: "owner-uid".to_string(),
+            fields: vec![RecordField::new("fileRef", vec![json!(file_uid.clone())])],
+            ..Record::default()
+        };
+
+        let mut written = Vec::new();
+        let bytes_written = sm.download_file_to_writer(&owner_record, &file_uid, &mut written).unwrap();
+        assert_eq!(bytes_written, plaintext.len() as u64);
+        assert_eq!(written, plaintext);
+    }
+
+    #[test]
+    fn download_file_to_writer_rejects_a_file_uid_absent_from_the_owner_record() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let owner_record = Record {
+            uid: "owner-uid".to_string(),
+            fields: vec![RecordField::new("fileRef", vec![json!("some-other-file-uid")])],
+            ..Record::default()
+        };
+
+        let mut written = Vec::new();
+        let err = sm.download_file_to_writer(&owner_record, "missing-file-uid", &mut written).unwrap_err();
+        assert!(matches!(err, KSMRError::RecordNotFound(_)));
+    }
+
+    #[test]
+    fn download_file_data_retries_a_transient_failure_and_eventually_succeeds() {
+        let attempts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let attempts_clone = attempts.clone();
+        let download_override: Arc<FileDownloadFn> = Arc::new(move |_url| {
+            if attempts_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < 2 {
+                Err(KSMRError::Network("connection reset".into()))
+            } else {
+                Ok(b"hello".to_vec())
+            }
+        });
+        let options = ClientOptions {
+            file_download_override: Some(download_override),
+            download_retries: 2,
+            ..bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })))
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        assert_eq!(sm.download_file_data("https://download.example/file").unwrap(), b"hello");
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn download_file_data_gives_up_after_exhausting_its_retries() {
+        let attempts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let attempts_clone = attempts.clone();
+        let download_override: Arc<FileDownloadFn> = Arc::new(move |_url| {
+            attempts_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Err(KSMRError::Network("connection reset".into()))
+        });
+        let options = ClientOptions {
+            file_download_override: Some(download_override),
+            download_retries: 2,
+            ..bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })))
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        assert!(sm.download_file_data("https://download.example/file").is_err());
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn list_attachments_reports_metadata_without_downloading_content() {
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let app_key = crypto::generate_encryption_key_bytes();
+        let file_key = crypto::generate_encryption_key_bytes();
+        let file_uid = crate::utils::generate_uid();
+        let file_meta_json =
+            serde_json::json!({"name": "a.txt", "size": 5, "title": "A", "lastModified": 1700000000000u64, "type": "text/plain"});
+        let file_blob = crypto::encrypt_aes_gcm(&file_key, file_meta_json.to_string().as_bytes()).unwrap();
+        let wrapped_file_key = crypto::encrypt_aes_gcm(&app_key, &file_key).unwrap();
+
+        let file_uid_resp = file_uid.clone();
+        let options = bound_options(Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let response_json = serde_json::json!({
+                "records": [{
+                    "recordUid": file_uid_resp,
+                    "recordKey": bytes_to_base64(&wrapped_file_key),
+                    "data": bytes_to_base64(&file_blob),
+                }],
+            });
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let owner_record = Record {
+            uid: "owner-uid".to_string(),
+            fields: vec![RecordField::new("fileRef", vec![json!(file_uid.clone())])],
+            ..Record::default()
+        };
+
+        let attachments = sm.list_attachments(&owner_record).unwrap();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].uid, file_uid);
+        assert_eq!(attachments[0].name, "a.txt");
+        assert_eq!(attachments[0].size, 5);
+        assert_eq!(attachments[0].title, "A");
+        assert_eq!(attachments[0].mime_type, "text/plain");
+    }
+
+    #[test]
+    fn list_attachments_returns_empty_for_a_record_with_no_file_ref() {
+        let options = bound_options(Arc::new(|_, _, _| Ok(KsmHttpResponse { status_code: 200, data: vec![] })));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let owner_record = Record { uid: "owner-uid".to_string(), ..Record::default() };
+        let attachments = sm.list_attachments(&owner_record).unwrap();
+        assert!(attachments.is_empty());
+    }
+
+    /// Private counterpart of `KEEPER_PUBLIC_KEYS["10"]`, used only here to stand in for the
+    /// gateway so the full request/response crypto pipeline can be exercised without a network.
+    const TEST_SERVER_PRIVATE_KEY_B64: &str = "3lduWCZk8swePoIt7TuBKXlJ9-2uvoJylsDBOzNAMVw";
+
+    /// Length of `public_encrypt`'s output when wrapping a 32-byte transmission key:
+    /// a 65-byte uncompressed point, 12-byte nonce, 32-byte ciphertext and 16-byte tag.
+    const ENCRYPTED_TRANSMISSION_KEY_LEN: usize = 65 + 12 + 32 + 16;
+
+    /// Round-trips a full `get_secret` exchange through a fake transport standing in for the
+    /// gateway: binds with a freshly generated app key, then resolves a `cardRef` to its
+    /// linked address record via `get_secret_with_links`.
+    #[test]
+    fn get_secret_with_links_resolves_referenced_records() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let card_uid = crate::utils::generate_uid();
+        let address_uid = crate::utils::generate_uid();
+
+        let address_json = serde_json::json!({"title": "Home", "type": "address", "fields": [], "custom": []});
+        let address_blob = crypto::encrypt_aes_gcm(&app_key, address_json.to_string().as_bytes()).unwrap();
+
+        let card_json = serde_json::json!({
+            "title": "My Card",
+            "type": "bankCard",
+            "fields": [{"type": "addressRef", "value": [address_uid.clone()]}],
+            "custom": [],
+        });
+        let card_blob = crypto::encrypt_aes_gcm(&app_key, card_json.to_string().as_bytes()).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let card_uid_resp = card_uid.clone();
+        let address_uid_resp = address_uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+
+            // Only the first request (binding) carries the wrapped app key, same as a real gateway.
+            let mut response_json = serde_json::json!({
+                "records": [
+                    {"recordUid": card_uid_resp, "data": bytes_to_base64(&card_blob)},
+                    {"recordUid": address_uid_resp, "data": bytes_to_base64(&address_blob)},
+                ],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let config = Arc::new(InMemoryKeyValueStorage::new());
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            config,
+            custom_post_function: Some(custom),
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        let linked = sm.get_secret_with_links(&card_uid).unwrap();
+        assert_eq!(linked.record.uid, card_uid);
+        assert_eq!(linked.linked.get(&address_uid).unwrap().title, "Home");
+        assert!(linked.unresolved.is_empty());
+    }
+
+    /// A `cardRef` pointing at a uid the gateway never returns - deleted, or simply not
+    /// shared to this application - is reported via `unresolved` instead of just vanishing
+    /// from `linked` with no trace.
+    #[test]
+    fn get_secret_with_links_reports_a_dangling_reference_as_unresolved() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let card_uid = crate::utils::generate_uid();
+        let missing_address_uid = crate::utils::generate_uid();
+
+        let card_json = serde_json::json!({
+            "title": "My Card",
+            "type": "bankCard",
+            "fields": [{"type": "addressRef", "value": [missing_address_uid.clone()]}],
+            "custom": [],
+        });
+        let card_blob = crypto::encrypt_aes_gcm(&app_key, card_json.to_string().as_bytes()).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let card_uid_resp = card_uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+
+            // The gateway omits the dangling addressRef target entirely rather than erroring.
+            let mut response_json = serde_json::json!({
+                "records": [
+                    {"recordUid": card_uid_resp, "data": bytes_to_base64(&card_blob)},
+                ],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let config = Arc::new(InMemoryKeyValueStorage::new());
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            config,
+            custom_post_function: Some(custom),
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        let linked = sm.get_secret_with_links(&card_uid).unwrap();
+        assert_eq!(linked.record.uid, card_uid);
+        assert!(!linked.linked.contains_key(&missing_address_uid));
+        assert_eq!(linked.unresolved, vec![missing_address_uid]);
+    }
+
+    #[test]
+    fn get_secrets_lossy_reads_a_record_with_invalid_utf8_that_get_secrets_rejects() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let uid = crate::utils::generate_uid();
+
+        // A hand-built JSON blob with one stray non-UTF-8 byte inside a string value,
+        // the way a legacy import might corrupt a single field without breaking the
+        // surrounding JSON structure (which stays plain ASCII).
+        let mut corrupt_json = br#"{"title": "Legacy "#.to_vec();
+        corrupt_json.push(0xff);
+        corrupt_json.extend_from_slice(br#" note", "type": "login", "fields": [], "custom": []}"#);
+        let blob = crypto::encrypt_aes_gcm(&app_key, &corrupt_json).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let uid_resp = uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let mut response_json = serde_json::json!({
+                "records": [{"recordUid": uid_resp, "data": bytes_to_base64(&blob)}],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        assert!(sm.get_secrets(None).is_err());
+
+        let records = sm.get_secrets_lossy(None).unwrap();
+        assert_eq!(records.len(), 1);
+        assert!(records[0].title.contains('\u{FFFD}'));
+    }
+
+    #[test]
+    fn fetch_and_decrypt_secrets_skips_an_undecryptable_folder_record_and_warns() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let folder_uid = crate::utils::generate_uid();
+        let good_uid = crate::utils::generate_uid();
+        let bad_uid = crate::utils::generate_uid();
+
+        let folder_key = crypto::generate_encryption_key_bytes();
+        let encrypted_folder_key = crypto::encrypt_aes_gcm(&app_key, &folder_key).unwrap();
+
+        let good_json = serde_json::json!({"title": "Good", "type": "login", "fields": [], "custom": []});
+        let good_blob = crypto::encrypt_aes_gcm(&folder_key, good_json.to_string().as_bytes()).unwrap();
+
+        // Encrypted under a key the folder's own key can't unwrap - stands in for a
+        // record re-keyed to a share this app never received.
+        let other_key = crypto::generate_encryption_key_bytes();
+        let bad_json = serde_json::json!({"title": "Bad", "type": "login", "fields": [], "custom": []});
+        let bad_blob = crypto::encrypt_aes_gcm(&other_key, bad_json.to_string().as_bytes()).unwrap();
+
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let folder_uid_resp = folder_uid.clone();
+        let good_uid_resp = good_uid.clone();
+        let bad_uid_resp = bad_uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let mut response_json = serde_json::json!({
+                "folders": [{
+                    "folderUid": folder_uid_resp,
+                    "folderKey": bytes_to_base64(&encrypted_folder_key),
+                    "records": [
+                        {"recordUid": good_uid_resp, "data": bytes_to_base64(&good_blob)},
+                        {"recordUid": bad_uid_resp, "data": bytes_to_base64(&bad_blob)},
+                    ],
+                }],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        // The first response only carries the wrapped app key; re-fetch to get records,
+        // mirroring what SecretsManager::get_secrets does on `just_bound`.
+        sm.fetch_and_decrypt_secrets(None).unwrap();
+        let response = sm.fetch_and_decrypt_secrets(None).unwrap();
+        assert!(!response.just_bound);
+
+        assert_eq!(response.records.len(), 1);
+        assert_eq!(response.records[0].uid, good_uid);
+        assert_eq!(response.warnings.len(), 1);
+        assert!(response.warnings[0].contains(&bad_uid));
+        assert!(response.warnings[0].contains(&folder_uid));
+    }
+
+    #[test]
+    fn fetch_and_decrypt_secrets_recovers_a_folder_record_wrapped_under_the_app_key() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let folder_uid = crate::utils::generate_uid();
+        let record_uid = crate::utils::generate_uid();
+
+        let folder_key = crypto::generate_encryption_key_bytes();
+        let encrypted_folder_key = crypto::encrypt_aes_gcm(&app_key, &folder_key).unwrap();
+
+        // This record's own key is wrapped under the application key, not the
+        // folder key its listing would normally imply - the mismatch this
+        // fallback exists for.
+        let record_key = crypto::generate_encryption_key_bytes();
+        let wrapped_record_key_under_app_key = crypto::encrypt_aes_gcm(&app_key, &record_key).unwrap();
+        let record_json = serde_json::json!({"title": "Cross-keyed", "type": "login", "fields": [], "custom": []});
+        let record_blob = crypto::encrypt_aes_gcm(&record_key, record_json.to_string().as_bytes()).unwrap();
+
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let folder_uid_resp = folder_uid.clone();
+        let record_uid_resp = record_uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let mut response_json = serde_json::json!({
+                "folders": [{
+                    "folderUid": folder_uid_resp,
+                    "folderKey": bytes_to_base64(&encrypted_folder_key),
+                    "records": [
+                        {
+                            "recordUid": record_uid_resp,
+                            "recordKey": bytes_to_base64(&wrapped_record_key_under_app_key),
+                            "data": bytes_to_base64(&record_blob),
+                        },
+                    ],
+                }],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        sm.fetch_and_decrypt_secrets(None).unwrap();
+        let response = sm.fetch_and_decrypt_secrets(None).unwrap();
+
+        assert_eq!(response.records.len(), 1);
+        assert_eq!(response.records[0].uid, record_uid);
+        assert_eq!(response.records[0].title, "Cross-keyed");
+        assert_eq!(response.warnings.len(), 1);
+        assert!(response.warnings[0].contains(&record_uid));
+        assert!(response.warnings[0].contains("application"));
+    }
+
+    #[test]
+    fn get_secrets_with_options_filters_to_the_requested_folder() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let top_uid = crate::utils::generate_uid();
+        let folder_uid = crate::utils::generate_uid();
+        let foldered_uid = crate::utils::generate_uid();
+
+        let top_json = serde_json::json!({"title": "Top", "type": "login", "fields": [], "custom": []});
+        let top_blob = crypto::encrypt_aes_gcm(&app_key, top_json.to_string().as_bytes()).unwrap();
+
+        let folder_key = crypto::generate_encryption_key_bytes();
+        let encrypted_folder_key = crypto::encrypt_aes_gcm(&app_key, &folder_key).unwrap();
+        let foldered_json = serde_json::json!({"title": "Foldered", "type": "login", "fields": [], "custom": []});
+        let foldered_blob = crypto::encrypt_aes_gcm(&folder_key, foldered_json.to_string().as_bytes()).unwrap();
+
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let top_uid_resp = top_uid.clone();
+        let folder_uid_resp = folder_uid.clone();
+        let foldered_uid_resp = foldered_uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+
+            let mut response_json = serde_json::json!({
+                "records": [{"recordUid": top_uid_resp, "data": bytes_to_base64(&top_blob)}],
+                "folders": [{
+                    "folderUid": folder_uid_resp,
+                    "folderKey": bytes_to_base64(&encrypted_folder_key),
+                    "records": [{"recordUid": foldered_uid_resp, "data": bytes_to_base64(&foldered_blob)}],
+                }],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        let query = QueryOptions::builder().with_folder(folder_uid.clone()).build();
+        let records = sm.get_secrets_with_options(&query).unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].uid, foldered_uid);
+        assert_eq!(records[0].title, "Foldered");
+    }
+
+    #[test]
+    fn get_secret_by_title_finds_a_matching_record_among_several() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let first_uid = crate::utils::generate_uid();
+        let second_uid = crate::utils::generate_uid();
+        let first_json = serde_json::json!({"title": "First", "type": "bankAccount", "fields": [], "custom": []});
+        let first_blob = crypto::encrypt_aes_gcm(&app_key, first_json.to_string().as_bytes()).unwrap();
+        let second_json = serde_json::json!({"title": "Second", "type": "login", "fields": [], "custom": []});
+        let second_blob = crypto::encrypt_aes_gcm(&app_key, second_json.to_string().as_bytes()).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let first_uid_resp = first_uid.clone();
+        let second_uid_resp = second_uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let mut response_json = serde_json::json!({
+                "records": [
+                    {"recordUid": first_uid_resp, "data": bytes_to_base64(&first_blob)},
+                    {"recordUid": second_uid_resp, "data": bytes_to_base64(&second_blob)},
+                ],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        let found = sm.get_secret_by_title("Second").unwrap();
+        assert_eq!(found.unwrap().uid, second_uid);
+        assert!(sm.get_secret_by_title("Missing").unwrap().is_none());
+
+        let filtered = sm.get_secrets_filtered(|r| r.title == "Second").unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].uid, second_uid);
+
+        let all = sm.get_secrets_filtered(|_| true).unwrap();
+        assert_eq!(all.len(), 2);
+
+        let none = sm.get_secrets_filtered(|_| false).unwrap();
+        assert!(none.is_empty());
+
+        let by_title = sm.get_secrets_by_titles(&["Second".to_string(), "Missing".to_string()]).unwrap();
+        assert_eq!(by_title.len(), 1);
+        assert_eq!(by_title["Second"].len(), 1);
+        assert_eq!(by_title["Second"][0].uid, second_uid);
+        assert!(!by_title.contains_key("Missing"));
+
+        let record_types = sm.list_record_types().unwrap();
+        assert_eq!(record_types, vec![("bankAccount".to_string(), 1), ("login".to_string(), 1)]);
+    }
+
+    #[test]
+    fn find_duplicate_titles_maps_each_shared_title_to_its_uids_and_skips_unique_ones() {
+        let app_key = crypto::generate_encryption_key_bytes();
+        let first_uid = crate::utils::generate_uid();
+        let second_uid = crate::utils::generate_uid();
+        let unique_uid = crate::utils::generate_uid();
+        let first_json = serde_json::json!({"title": "Shared", "type": "login", "fields": [], "custom": []});
+        let first_blob = crypto::encrypt_aes_gcm(&app_key, first_json.to_string().as_bytes()).unwrap();
+        let second_json = serde_json::json!({"title": "Shared", "type": "login", "fields": [], "custom": []});
+        let second_blob = crypto::encrypt_aes_gcm(&app_key, second_json.to_string().as_bytes()).unwrap();
+        let unique_json = serde_json::json!({"title": "Unique", "type": "login", "fields": [], "custom": []});
+        let unique_blob = crypto::encrypt_aes_gcm(&app_key, unique_json.to_string().as_bytes()).unwrap();
+
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+        let first_uid_resp = first_uid.clone();
+        let second_uid_resp = second_uid.clone();
+        let unique_uid_resp = unique_uid.clone();
+        let options = bound_options(Arc::new(move |_, body, _| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let response_json = serde_json::json!({
+                "records": [
+                    {"recordUid": first_uid_resp, "data": bytes_to_base64(&first_blob)},
+                    {"recordUid": second_uid_resp, "data": bytes_to_base64(&second_blob)},
+                    {"recordUid": unique_uid_resp, "data": bytes_to_base64(&unique_blob)},
+                ],
+            });
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        }));
+        options.config.set(ConfigKey::AppKey, bytes_to_base64(&app_key));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let duplicates = sm.find_duplicate_titles().unwrap();
+
+        assert_eq!(duplicates.len(), 1);
+        let mut shared = duplicates["Shared"].clone();
+        shared.sort();
+        let mut expected = vec![first_uid, second_uid];
+        expected.sort();
+        assert_eq!(shared, expected);
+        assert!(!duplicates.contains_key("Unique"));
+    }
+
+    #[test]
+    fn has_expiration_before_matches_on_either_expiration_field_type() {
+        let expiring = Record {
+            fields: vec![RecordField::new("expirationDate", vec![serde_json::json!(1_000)])],
+            ..Record::default()
+        };
+        assert!(SecretsManager::has_expiration_before(&expiring, 2_000));
+        assert!(!SecretsManager::has_expiration_before(&expiring, 500));
+
+        let card = Record {
+            custom: vec![RecordField::new("cardExpirationDate", vec![serde_json::json!(1_000)])],
+            ..Record::default()
+        };
+        assert!(SecretsManager::has_expiration_before(&card, 2_000));
+
+        let no_expiration = Record {
+            fields: vec![RecordField::new("login", vec![serde_json::json!("alice")])],
+            ..Record::default()
+        };
+        assert!(!SecretsManager::has_expiration_before(&no_expiration, i64::MAX));
+
+        let malformed = Record {
+            fields: vec![RecordField::new("expirationDate", vec![serde_json::json!("not-a-number")])],
+            ..Record::default()
+        };
+        assert!(!SecretsManager::has_expiration_before(&malformed, i64::MAX));
+    }
+
+    #[test]
+    fn get_expiring_secrets_returns_only_records_within_the_window() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let soon_uid = crate::utils::generate_uid();
+        let far_uid = crate::utils::generate_uid();
+        let now_millis = crate::utils::now_milliseconds() as i64;
+        let soon_json = serde_json::json!({
+            "title": "Soon",
+            "type": "card",
+            "fields": [{"type": "cardExpirationDate", "value": [now_millis + 1_000]}],
+            "custom": [],
+        });
+        let soon_blob = crypto::encrypt_aes_gcm(&app_key, soon_json.to_string().as_bytes()).unwrap();
+        let far_json = serde_json::json!({
+            "title": "Far",
+            "type": "card",
+            "fields": [{"type": "cardExpirationDate", "value": [now_millis + 365 * 24 * 60 * 60 * 1_000]}],
+            "custom": [],
+        });
+        let far_blob = crypto::encrypt_aes_gcm(&app_key, far_json.to_string().as_bytes()).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let soon_uid_resp = soon_uid.clone();
+        let far_uid_resp = far_uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+            let mut response_json = serde_json::json!({
+                "records": [
+                    {"recordUid": soon_uid_resp, "data": bytes_to_base64(&soon_blob)},
+                    {"recordUid": far_uid_resp, "data": bytes_to_base64(&far_blob)},
+                ],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        let expiring = sm.get_expiring_secrets(Duration::from_secs(60)).unwrap();
+        assert_eq!(expiring.len(), 1);
+        assert_eq!(expiring[0].uid, soon_uid);
+    }
+
+    #[test]
+    fn list_record_metadata_omits_field_values() {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap()).unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let login_uid = crate::utils::generate_uid();
+        let login_json = serde_json::json!({
+            "title": "Example Login",
+            "type": "login",
+            "fields": [{"type": "password", "value": ["super-secret"]}],
+            "custom": [],
+        });
+        let login_blob = crypto::encrypt_aes_gcm(&app_key, login_json.to_string().as_bytes()).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let login_uid_resp = login_uid.clone();
+        let already_bound = std::sync::atomic::AtomicBool::new(false);
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+
+            let mut response_json = serde_json::json!({
+                "records": [{"recordUid": login_uid_resp, "data": bytes_to_base64(&login_blob)}],
+            });
+            if !already_bound.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            custom_post_function: Some(custom),
+            ..ClientOptions::default()
+        };
+        let sm = SecretsManager::new(options).unwrap();
+
+        let metadata = sm.list_record_metadata().unwrap();
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata[0].uid, login_uid);
+        assert_eq!(metadata[0].title, "Example Login");
+        assert_eq!(metadata[0].record_type, "login");
+    }
+
+    #[test]
+    fn record_type_schemas_covers_login_without_a_network_call() {
+        let options = bound_options(Arc::new(|_, _, _| {
+            panic!("record_type_schemas should not make a network call");
+        }));
+        let sm = SecretsManager::new(options).unwrap();
+
+        let schemas = sm.record_type_schemas();
+        let login = schemas.iter().find(|s| s.record_type == "login").unwrap();
+        assert!(login.fields.iter().any(|f| f.field_type == "password" && f.required));
+    }
+}
@@ -0,0 +1,260 @@
+//! Low-level cryptographic primitives: key generation, AES-GCM record encryption
+//! and the EC transmission-key exchange used when talking to the Keeper gateway.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use p256::ecdh::diffie_hellman;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::{PublicKey, SecretKey};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Digest;
+
+use crate::error::KSMRError;
+
+/// Seam for supplying randomness to UID/key generation. Production code uses
+/// [`OsRngProvider`] (backed by the OS CSPRNG); tests can supply a seeded,
+/// deterministic implementation so generated payloads can be snapshot-tested.
+pub trait RngProvider: Send + Sync {
+    fn fill_bytes(&self, buf: &mut [u8]);
+}
+
+/// Default [`RngProvider`] backed by the operating system's CSPRNG.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OsRngProvider;
+
+impl RngProvider for OsRngProvider {
+    fn fill_bytes(&self, buf: &mut [u8]) {
+        OsRng.fill_bytes(buf);
+    }
+}
+
+/// Fills `length` bytes of randomness using the given provider.
+pub fn generate_random_bytes_with(rng: &dyn RngProvider, length: usize) -> Vec<u8> {
+    let mut buf = vec![0u8; length];
+    rng.fill_bytes(&mut buf);
+    buf
+}
+
+/// Fills `length` bytes of randomness using the OS CSPRNG.
+pub fn generate_random_bytes(length: usize) -> Vec<u8> {
+    generate_random_bytes_with(&OsRngProvider, length)
+}
+
+/// Generates a 256-bit AES encryption key using the given randomness source.
+pub fn generate_encryption_key_bytes_with(rng: &dyn RngProvider) -> Vec<u8> {
+    generate_random_bytes_with(rng, 32)
+}
+
+/// Generates a 256-bit AES encryption key using the OS CSPRNG.
+pub fn generate_encryption_key_bytes() -> Vec<u8> {
+    generate_encryption_key_bytes_with(&OsRngProvider)
+}
+
+/// Generates a new P-256 (secp256r1) private key using the given randomness source.
+pub fn generate_private_key_ecc_with(rng: &dyn RngProvider) -> Result<SecretKey, KSMRError> {
+    loop {
+        let bytes = generate_random_bytes_with(rng, 32);
+        if let Ok(key) = SecretKey::from_slice(&bytes) {
+            return Ok(key);
+        }
+        // Extremely unlikely: sampled scalar was out of range. Retry with fresh bytes.
+    }
+}
+
+/// Generates a new P-256 private key using the OS CSPRNG.
+pub fn generate_private_key_ecc() -> Result<SecretKey, KSMRError> {
+    generate_private_key_ecc_with(&OsRngProvider)
+}
+
+/// Returns the uncompressed SEC1 encoding of `key`'s public point.
+pub fn public_key_ecc(key: &SecretKey) -> Vec<u8> {
+    key.public_key().to_encoded_point(false).as_bytes().to_vec()
+}
+
+/// Encrypts `plaintext` with AES-256-GCM using a random 12-byte nonce, which is
+/// prepended to the returned ciphertext (the format used throughout the backend API).
+pub fn encrypt_aes_gcm(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, KSMRError> {
+    encrypt_aes_gcm_with(&OsRngProvider, key, plaintext)
+}
+
+/// Like [`encrypt_aes_gcm`] but with an injectable randomness source for the nonce.
+pub fn encrypt_aes_gcm_with(
+    rng: &dyn RngProvider,
+    key: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, KSMRError> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| KSMRError::Crypto(e.to_string()))?;
+    let nonce_bytes = generate_random_bytes_with(rng, 12);
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let ciphertext = cipher
+        .encrypt(nonce, Payload { msg: plaintext, aad: &[] })
+        .map_err(|e| KSMRError::Crypto(e.to_string()))?;
+    let mut out = nonce_bytes;
+    out.extend(ciphertext);
+    Ok(out)
+}
+
+/// Decrypts data produced by [`encrypt_aes_gcm`] (12-byte nonce prefix + ciphertext).
+pub fn decrypt_aes_gcm(key: &[u8], data: &[u8]) -> Result<Vec<u8>, KSMRError> {
+    if data.len() < 12 {
+        return Err(KSMRError::Crypto("ciphertext too short".into()));
+    }
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| KSMRError::Crypto(e.to_string()))?;
+    let (nonce_bytes, ciphertext) = data.split_at(12);
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(nonce_bytes);
+    cipher
+        .decrypt(nonce, Payload { msg: ciphertext, aad: &[] })
+        .map_err(|e| KSMRError::Crypto(e.to_string()))
+}
+
+/// Derives a shared AES key from our ephemeral private key and the server's
+/// public key, used to wrap the transmission key sent on every request.
+pub fn derive_shared_key(private_key: &SecretKey, server_public_key: &[u8]) -> Result<Vec<u8>, KSMRError> {
+    let public_key =
+        PublicKey::from_sec1_bytes(server_public_key).map_err(|e| KSMRError::Crypto(e.to_string()))?;
+    let shared = diffie_hellman(private_key.to_nonzero_scalar(), public_key.as_affine());
+    let digest = sha2::Sha256::digest(shared.raw_secret_bytes());
+    Ok(digest.to_vec())
+}
+
+/// Encrypts `data` so that only the holder of the private key matching
+/// `recipient_public_key_raw` (an uncompressed SEC1 point) can decrypt it.
+/// Used both to wrap the per-request transmission key (for the server) and,
+/// in reverse, by the server to hand us the application key during binding.
+pub fn public_encrypt(data: &[u8], recipient_public_key_raw: &[u8]) -> Result<Vec<u8>, KSMRError> {
+    public_encrypt_with(&OsRngProvider, data, recipient_public_key_raw)
+}
+
+pub fn public_encrypt_with(
+    rng: &dyn RngProvider,
+    data: &[u8],
+    recipient_public_key_raw: &[u8],
+) -> Result<Vec<u8>, KSMRError> {
+    let ephemeral = generate_private_key_ecc_with(rng)?;
+    let shared_key = derive_shared_key(&ephemeral, recipient_public_key_raw)?;
+    let ciphertext = encrypt_aes_gcm_with(rng, &shared_key, data)?;
+    let mut out = public_key_ecc(&ephemeral);
+    out.extend(ciphertext);
+    Ok(out)
+}
+
+/// Inverse of [`public_encrypt`]: unwraps a payload encrypted for `private_key`'s public point.
+pub fn private_decrypt(data: &[u8], private_key: &SecretKey) -> Result<Vec<u8>, KSMRError> {
+    if data.len() < 65 {
+        return Err(KSMRError::Crypto("encrypted payload too short".into()));
+    }
+    let (ephemeral_public_raw, ciphertext) = data.split_at(65);
+    let shared_key = derive_shared_key(private_key, ephemeral_public_raw)?;
+    decrypt_aes_gcm(&shared_key, ciphertext)
+}
+
+/// Signs `data` with ECDSA/P-256, returning a DER-encoded signature, so the
+/// server can detect tampering with the transmission key + encrypted payload.
+pub fn sign(data: &[u8], private_key: &SecretKey) -> Vec<u8> {
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::{Signature, SigningKey};
+    let signing_key = SigningKey::from(private_key.clone());
+    let signature: Signature = signing_key.sign(data);
+    signature.to_der().as_bytes().to_vec()
+}
+
+/// Verifies a DER-encoded ECDSA/P-256 signature produced by [`sign`]. Used as
+/// a debug-only self-check after signing on the request hot path (see
+/// `SecretsManager::encrypt_and_sign_payload`) - the server performs the
+/// verification that actually matters, so this is not part of the
+/// release-build request flow.
+pub fn verify(data: &[u8], signature_der: &[u8], private_key: &SecretKey) -> bool {
+    use p256::ecdsa::signature::Verifier;
+    use p256::ecdsa::{Signature, VerifyingKey};
+    let Ok(signature) = Signature::from_der(signature_der) else {
+        return false;
+    };
+    let verifying_key = VerifyingKey::from(private_key.public_key());
+    verifying_key.verify(data, &signature).is_ok()
+}
+
+/// Seam for the signature scheme [`crate::client::SecretsManager`] uses to
+/// authenticate requests to the gateway, so a future alternative scheme can
+/// be added without touching `client.rs`. [`P256Signer`] (ECDSA/P-256, via
+/// [`sign`]/[`verify`]) is the only scheme the gateway currently accepts, and
+/// is what [`crate::client::ClientOptions::default`] configures; this trait
+/// exists so that can change later, and so signing is mockable in tests.
+pub trait Signer: Send + Sync {
+    fn sign(&self, data: &[u8], private_key: &SecretKey) -> Vec<u8>;
+    fn verify(&self, data: &[u8], signature: &[u8], private_key: &SecretKey) -> bool;
+}
+
+/// Default [`Signer`]: ECDSA/P-256 via the free [`sign`]/[`verify`] functions.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct P256Signer;
+
+impl Signer for P256Signer {
+    fn sign(&self, data: &[u8], private_key: &SecretKey) -> Vec<u8> {
+        sign(data, private_key)
+    }
+
+    fn verify(&self, data: &[u8], signature: &[u8], private_key: &SecretKey) -> bool {
+        verify(data, signature, private_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct CountingRng(std::sync::atomic::AtomicU8);
+    impl RngProvider for CountingRng {
+        fn fill_bytes(&self, buf: &mut [u8]) {
+            for b in buf.iter_mut() {
+                *b = self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+    }
+
+    #[test]
+    fn deterministic_rng_is_reproducible() {
+        let rng = CountingRng(std::sync::atomic::AtomicU8::new(0));
+        let first = generate_random_bytes_with(&rng, 8);
+        let rng2 = CountingRng(std::sync::atomic::AtomicU8::new(0));
+        let second = generate_random_bytes_with(&rng2, 8);
+        assert_eq!(first, second);
+        assert_eq!(first, vec![0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn ecies_round_trips() {
+        let recipient = generate_private_key_ecc().unwrap();
+        let recipient_public = public_key_ecc(&recipient);
+        let encrypted = public_encrypt(b"application key", &recipient_public).unwrap();
+        let decrypted = private_decrypt(&encrypted, &recipient).unwrap();
+        assert_eq!(decrypted, b"application key");
+    }
+
+    #[test]
+    fn sign_then_verify_round_trips() {
+        let key = generate_private_key_ecc().unwrap();
+        let signature = sign(b"encrypted transmission key + payload", &key);
+        assert!(verify(b"encrypted transmission key + payload", &signature, &key));
+        assert!(!verify(b"tampered data", &signature, &key));
+    }
+
+    #[test]
+    fn p256_signer_round_trips_through_the_signer_trait() {
+        let key = generate_private_key_ecc().unwrap();
+        let signer = P256Signer;
+        let signature = signer.sign(b"encrypted transmission key + payload", &key);
+        assert!(signer.verify(b"encrypted transmission key + payload", &signature, &key));
+        assert!(!signer.verify(b"tampered data", &signature, &key));
+    }
+
+    #[test]
+    fn aes_gcm_round_trips() {
+        let key = generate_encryption_key_bytes();
+        let ciphertext = encrypt_aes_gcm(&key, b"hello world").unwrap();
+        let plaintext = decrypt_aes_gcm(&key, &ciphertext).unwrap();
+        assert_eq!(plaintext, b"hello world");
+    }
+}
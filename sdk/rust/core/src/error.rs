@@ -0,0 +1,55 @@
+use thiserror::Error;
+
+/// Error type returned by all fallible operations in this crate.
+#[derive(Debug, Error)]
+pub enum KSMRError {
+    #[error("configuration error: {0}")]
+    Config(String),
+
+    #[error("network error: {0}")]
+    Network(String),
+
+    #[error("crypto error: {0}")]
+    Crypto(String),
+
+    #[error("record not found: {0}")]
+    RecordNotFound(String),
+
+    #[error("folder not found: {0}")]
+    FolderNotFound(String),
+
+    /// The network fetch that completes binding - the first request a
+    /// client makes before [`crate::storage::ConfigKey::AppKey`] is set -
+    /// didn't finish within [`crate::client::ClientOptions::bind_timeout`].
+    /// Distinguished from a plain [`KSMRError::Network`] so a short-lived
+    /// caller (e.g. a serverless function) can fail fast and distinctly on a
+    /// hung bind specifically, rather than treating it the same as any other
+    /// transient network error.
+    #[error("binding timed out after {0:?}")]
+    BindTimeout(std::time::Duration),
+
+    /// A [`crate::notation`] string is malformed - missing its uid, missing
+    /// its selector, an unterminated `[...]` index, or similar. `position` is
+    /// the byte offset into `notation` where the problem starts, so a
+    /// caller (e.g. a config-linter) can underline the exact bad character
+    /// instead of just showing `message`. Errors that depend on the actual
+    /// record's shape rather than the notation string's syntax (an unknown
+    /// field, an out-of-range index) are not represented here, since there's
+    /// no fixed position in the string to blame for those.
+    #[error("{message} (at position {position} in notation '{notation}')")]
+    Notation { message: String, position: usize, notation: String },
+
+    /// The gateway rejected this SDK's `clientVersion` string (sent with every
+    /// request, see `KEEPER_SECRETS_MANAGER_SDK_CLIENT_ID`). The wrapped
+    /// string is the server's `additional_info`, if it sent one. This is
+    /// almost always fixed by upgrading `keeper-secrets-manager-core`: the
+    /// backend has dropped support for the client id this build sends.
+    #[error("server rejected this SDK's client version ({0}); upgrade keeper-secrets-manager-core to a newer release")]
+    ClientVersion(String),
+
+    #[error("serialization error: {0}")]
+    Serialization(String),
+
+    #[error("{0}")]
+    Other(String),
+}
@@ -0,0 +1,28 @@
+//! Constants shared across the SDK, mirroring `keeper_globals.py` in the Python core SDK.
+
+pub const KEEPER_SECRETS_MANAGER_SDK_CLIENT_ID: &str = "mr11.1.0";
+pub const DEFAULT_KEY_ID: &str = "10";
+pub const NOTATION_PREFIX: &str = "keeper";
+
+/// Keeper region abbreviation -> hostname, used when a one-time token is
+/// prefixed with a region code (e.g. `US:ONE_TIME_TOKEN`).
+pub const KEEPER_SERVERS: &[(&str, &str)] = &[
+    ("US", "keepersecurity.com"),
+    ("EU", "keepersecurity.eu"),
+    ("AU", "keepersecurity.com.au"),
+    ("GOV", "govcloud.keepersecurity.us"),
+    ("JP", "keepersecurity.jp"),
+    ("CA", "keepersecurity.ca"),
+];
+
+pub fn keeper_server_for_abbreviation(abbreviation: &str) -> Option<&'static str> {
+    KEEPER_SERVERS
+        .iter()
+        .find(|(abbr, _)| abbr.eq_ignore_ascii_case(abbreviation))
+        .map(|(_, host)| *host)
+}
+
+/// Returns the full table of Keeper region abbreviations and their hostnames.
+pub fn get_keeper_servers() -> &'static [(&'static str, &'static str)] {
+    KEEPER_SERVERS
+}
@@ -0,0 +1,19 @@
+//! Keeper Secrets Manager SDK for Rust.
+//!
+//! This crate mirrors the structure of the Python core SDK
+//! (`sdk/python/core/keeper_secrets_manager_core`): binding and transmission
+//! handled by [`client`], record/folder shapes in [`dto`], cryptography in
+//! [`crypto`], configuration storage in [`storage`] and general helpers in [`utils`].
+
+pub mod client;
+pub mod crypto;
+pub mod dto;
+pub mod error;
+pub mod keeper_globals;
+pub mod notation;
+pub mod payload;
+pub mod storage;
+pub mod utils;
+
+pub use client::{ClientOptions, SecretsManager};
+pub use error::KSMRError;
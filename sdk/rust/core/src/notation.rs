@@ -0,0 +1,641 @@
+//! Keeper notation: a compact string syntax for addressing a single value
+//! inside a record, e.g. `keeper://<uid>/field/password`. Mirrors the
+//! `get_notation` helper in the Python core SDK.
+//!
+//! This is a minimal implementation covering the `title`, `type`, `notes`,
+//! `field` and `custom_field` selectors by exact label-or-type match. The
+//! index predicate on `field`/`custom_field` is supported, with these
+//! semantics:
+//!
+//! - no `[...]` at all - the field's first value, same as `[0]`.
+//! - `[n]` - the value at index `n`, as a single value.
+//! - `[]` - every value, distinguished from a single value by shape rather than
+//!   by a separate call: [`SecretsManager::get_notation`] returns them JSON-array-encoded
+//!   (`["a","b"]`), while [`SecretsManager::get_notation_results`] returns them as a
+//!   native `Vec<String>` with no encoding.
+//!
+//! A second `[key]` predicate may follow the index predicate (`phone[0][number]`,
+//! `name[][first]`) to pull one property out of an object-shaped value, e.g. a
+//! `name` field's `{"first": ..., "last": ...}` value. It's parsed by
+//! [`parse_key_index`] alongside the index predicate and applied to every value
+//! the index predicate selected; a value the dictionary-key predicate doesn't
+//! apply to (not an object, or missing that property) is a [`KSMRError::Other`].
+//!
+//! [`SecretsManager::get_notation_results`] applies the same index and
+//! dictionary-key rules, so the two functions never disagree about which values
+//! a notation selects, only about how they're packaged for the caller.
+
+use crate::client::SecretsManager;
+use crate::error::KSMRError;
+use crate::keeper_globals::NOTATION_PREFIX;
+use serde_json::Value;
+
+/// Which of a multi-valued field's values a parsed notation selects.
+enum Index {
+    /// No `[...]` predicate was given - use the first value, same as `One(0)`.
+    Default,
+    /// `[n]` - exactly one value, at this position.
+    One(usize),
+    /// `[]` - every value.
+    All,
+}
+
+/// Splits a selector key like `password`, `url[1]` or `name[][first]` into
+/// the bare key, its index predicate, and an optional trailing
+/// dictionary-key predicate. At most two `[...]` groups are accepted - one
+/// index predicate (numeric or empty) followed by at most one dictionary-key
+/// predicate (a non-empty property name); anything else, including a third
+/// group, text between or after the groups, or an unterminated `[`, is a
+/// [`KSMRError::Notation`]. `notation` is the full original string and
+/// `key_pos` is `key`'s byte offset within it, purely so error positions can
+/// be reported.
+fn parse_key_index<'a>(
+    notation: &str,
+    key_pos: usize,
+    key: &'a str,
+) -> Result<(&'a str, Index, Option<&'a str>), KSMRError> {
+    let Some(open) = key.find('[') else {
+        return Ok((key, Index::Default, None));
+    };
+    let bare_key = &key[..open];
+    let groups = parse_bracket_groups(notation, key_pos, key, open)?;
+
+    let (index_pos, index_predicate) = groups[0];
+    let index = if index_predicate.is_empty() {
+        Index::All
+    } else {
+        let value = index_predicate.parse::<usize>().map_err(|_| {
+            notation_error(
+                notation,
+                index_pos,
+                format!("notation index '[{index_predicate}]' is not a non-negative integer"),
+            )
+        })?;
+        Index::One(value)
+    };
+
+    let dict_key = match groups.get(1) {
+        None => None,
+        Some(&(dict_key_pos, dict_key)) => {
+            if dict_key.is_empty() {
+                return Err(notation_error(
+                    notation,
+                    dict_key_pos,
+                    format!(
+                        "notation key '{key}' has an empty dictionary-key predicate '[]' where a property name was expected"
+                    ),
+                ));
+            }
+            Some(dict_key)
+        }
+    };
+
+    Ok((bare_key, index, dict_key))
+}
+
+/// Consumes every `[...]` group starting at byte offset `open` within `key`,
+/// returning each group's contents paired with its own byte offset within
+/// `notation` (for error reporting) - at most two groups, anything past that
+/// is rejected as unsupported. `key_pos` is `key`'s own offset within
+/// `notation`, matched by [`parse_key_index`]'s callers.
+fn parse_bracket_groups<'a>(
+    notation: &str,
+    key_pos: usize,
+    key: &'a str,
+    open: usize,
+) -> Result<Vec<(usize, &'a str)>, KSMRError> {
+    let mut groups = Vec::with_capacity(2);
+    let mut rest = &key[open..];
+    let mut pos = open;
+    loop {
+        if !rest.starts_with('[') {
+            return Err(notation_error(
+                notation,
+                key_pos,
+                format!("notation key '{key}' has unexpected characters after its index predicate"),
+            ));
+        }
+        let Some(close) = rest.find(']') else {
+            return Err(notation_error(notation, key_pos, format!("notation key '{key}' has an unterminated '['")));
+        };
+        let inner = &rest[1..close];
+        if inner.contains('[') {
+            return Err(notation_error(
+                notation,
+                key_pos + pos,
+                format!("notation key '{key}' has more than one index predicate, which is not supported"),
+            ));
+        }
+        groups.push((key_pos + pos + 1, inner));
+        rest = &rest[close + 1..];
+        pos += close + 1;
+        if rest.is_empty() {
+            return Ok(groups);
+        }
+        if groups.len() == 2 {
+            return Err(notation_error(
+                notation,
+                key_pos + pos,
+                format!("notation key '{key}' has more than two index predicates, which is not supported"),
+            ));
+        }
+    }
+}
+
+/// Applies an optional dictionary-key predicate to a single selected value:
+/// `None` passes `value` through unchanged, `Some(dict_key)` requires
+/// `value` to be a JSON object with that property and returns just it.
+/// `field_key` is only used to name the field in the error message.
+fn select_property(value: &Value, dict_key: Option<&str>, field_key: &str) -> Result<Value, KSMRError> {
+    match dict_key {
+        None => Ok(value.clone()),
+        Some(dict_key) => value.as_object().and_then(|obj| obj.get(dict_key)).cloned().ok_or_else(|| {
+            KSMRError::Other(format!(
+                "notation key '{field_key}' selected a value with no '{dict_key}' property to index into"
+            ))
+        }),
+    }
+}
+
+/// Builds a [`KSMRError::Notation`] naming where in `notation` parsing broke.
+fn notation_error(notation: &str, position: usize, message: impl Into<String>) -> KSMRError {
+    KSMRError::Notation { message: message.into(), position, notation: notation.to_string() }
+}
+
+/// Splits `body` into at most 3 `/`-separated segments, like
+/// `body.splitn(3, '/')`, but keeping each segment's byte offset within
+/// `body` so callers can report precise error positions.
+fn split_segments(body: &str) -> Vec<(usize, &str)> {
+    let mut segments = Vec::with_capacity(3);
+    let mut rest = body;
+    let mut offset = 0;
+    for _ in 0..3 {
+        if segments.len() < 2 {
+            if let Some(idx) = rest.find('/') {
+                segments.push((offset, &rest[..idx]));
+                offset += idx + 1;
+                rest = &rest[idx + 1..];
+                continue;
+            }
+        }
+        segments.push((offset, rest));
+        break;
+    }
+    segments
+}
+
+/// Formats a JSON value the way `field`/`custom_field` notation values are
+/// rendered: strings pass through unquoted, everything else is JSON-encoded.
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+impl SecretsManager {
+    /// Resolves a notation string to a single string value - or, when the
+    /// selector ends in `[]`, to a JSON-encoded array of every value. Never
+    /// panics on a malformed or oddly-shaped record - every failure path
+    /// returns a [`KSMRError`] instead.
+    pub fn get_notation(&self, notation: &str) -> Result<String, KSMRError> {
+        match self.resolve_notation(notation)? {
+            Resolved::Single(s) => Ok(s),
+            Resolved::Many(values) => {
+                serde_json::to_string(&values).map_err(|e| KSMRError::Serialization(e.to_string()))
+            }
+        }
+    }
+
+    /// Like [`SecretsManager::get_notation`], but returns the selected values
+    /// as a native `Vec<String>` instead of a JSON-encoded string. A selector
+    /// with no index or a `[n]` index returns a single-element vec; `[]`
+    /// returns one element per value.
+    pub fn get_notation_results(&self, notation: &str) -> Result<Vec<String>, KSMRError> {
+        match self.resolve_notation(notation)? {
+            Resolved::Single(s) => Ok(vec![s]),
+            Resolved::Many(values) => Ok(values.into_iter().map(|v| format_value(&v)).collect()),
+        }
+    }
+
+    /// Resolves every notation in `notations` independently via
+    /// [`SecretsManager::get_notation`], keeping each one's error instead of
+    /// swallowing it - unlike a "best effort" helper that folds every
+    /// failure into an empty result, this lets a caller resolving a mix of
+    /// optional and required notations tell "this one just isn't set on the
+    /// record" apart from "this one is broken" by matching on the `Result`
+    /// itself. A notation repeated in `notations` is resolved once; later
+    /// occurrences overwrite earlier ones in the returned map, same as
+    /// inserting into a `HashMap` by hand would.
+    pub fn get_notations_partial(
+        &self,
+        notations: &[String],
+    ) -> std::collections::HashMap<String, Result<String, KSMRError>> {
+        notations
+            .iter()
+            .map(|notation| (notation.clone(), self.get_notation(notation)))
+            .collect()
+    }
+
+    fn resolve_notation(&self, notation: &str) -> Result<Resolved, KSMRError> {
+        let prefix = format!("{NOTATION_PREFIX}://");
+        let body_offset = if notation.starts_with(&prefix) { prefix.len() } else { 0 };
+        let body = &notation[body_offset..];
+
+        let segments = split_segments(body);
+        let (uid_pos, uid) = segments[0];
+        if uid.is_empty() {
+            return Err(notation_error(notation, body_offset + uid_pos, "notation is missing a record uid"));
+        }
+        let (selector_pos, selector) = segments.get(1).copied().unwrap_or((body.len(), ""));
+        if selector.is_empty() {
+            return Err(notation_error(notation, body_offset + selector_pos, "notation is missing a selector"));
+        }
+        let key = segments.get(2).map(|&(pos, s)| (body_offset + pos, s));
+
+        let records = self.get_secrets(Some(vec![uid.to_string()]))?;
+        let record = records
+            .into_iter()
+            .next()
+            .ok_or_else(|| KSMRError::RecordNotFound(uid.to_string()))?;
+
+        match selector {
+            "title" => Ok(Resolved::Single(record.title)),
+            "type" => Ok(Resolved::Single(record.record_type)),
+            "notes" => Ok(Resolved::Single(record.notes_text())),
+            "field" | "custom_field" => {
+                let (key_pos, key) = key.ok_or_else(|| {
+                    notation_error(
+                        notation,
+                        body_offset + selector_pos + selector.len(),
+                        format!("notation selector '{selector}' requires a field name"),
+                    )
+                })?;
+                let (key, index, dict_key) = parse_key_index(notation, key_pos, key)?;
+                let fields = if selector == "field" { &record.fields } else { &record.custom };
+                let field = fields
+                    .iter()
+                    .find(|f| f.field_type == key || f.label.as_deref() == Some(key))
+                    .ok_or_else(|| {
+                        KSMRError::Other(format!("field '{key}' not found in record {uid}"))
+                    })?;
+                match index {
+                    Index::All => {
+                        let values = field
+                            .value
+                            .iter()
+                            .map(|v| select_property(v, dict_key, key))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Ok(Resolved::Many(values))
+                    }
+                    Index::Default => {
+                        let value = match field.value.first() {
+                            Some(v) => Some(select_property(v, dict_key, key)?),
+                            None => None,
+                        };
+                        Ok(Resolved::Single(value.as_ref().map(format_value).unwrap_or_default()))
+                    }
+                    Index::One(i) => {
+                        let value = field.value.get(i).ok_or_else(|| {
+                            KSMRError::Other(format!(
+                                "index [{i}] is out of range for field '{key}', which has {} value(s)",
+                                field.value.len()
+                            ))
+                        })?;
+                        let value = select_property(value, dict_key, key)?;
+                        Ok(Resolved::Single(format_value(&value)))
+                    }
+                }
+            }
+            other => Err(notation_error(notation, body_offset + selector_pos, format!("unknown notation selector '{other}'"))),
+        }
+    }
+}
+
+/// What a notation resolved to, before it's packaged for the caller by
+/// [`SecretsManager::get_notation`] or [`SecretsManager::get_notation_results`].
+enum Resolved {
+    Single(String),
+    Many(Vec<Value>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{ClientOptions, CustomPostFn};
+    use crate::crypto;
+    use crate::payload::KsmHttpResponse;
+    use crate::storage::InMemoryKeyValueStorage;
+    use crate::utils::{bytes_to_base64, bytes_to_url_safe_str, generate_uid};
+    use p256::SecretKey;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    const TEST_SERVER_PRIVATE_KEY_B64: &str = "3lduWCZk8swePoIt7TuBKXlJ9-2uvoJylsDBOzNAMVw";
+    const ENCRYPTED_TRANSMISSION_KEY_LEN: usize = 65 + 12 + 32 + 16;
+
+    fn bound_sm_with_record(record_json: serde_json::Value) -> (SecretsManager, String) {
+        let token_bytes = crypto::generate_random_bytes(32);
+        let token = bytes_to_url_safe_str(&token_bytes);
+        let server_key =
+            SecretKey::from_slice(&crate::utils::url_safe_str_to_bytes(TEST_SERVER_PRIVATE_KEY_B64).unwrap())
+                .unwrap();
+
+        let app_key = crypto::generate_encryption_key_bytes();
+        let uid = generate_uid();
+        let blob = crypto::encrypt_aes_gcm(&app_key, record_json.to_string().as_bytes()).unwrap();
+        let encrypted_app_key = crypto::encrypt_aes_gcm(&token_bytes, &app_key).unwrap();
+
+        let uid_resp = uid.clone();
+        let already_bound = AtomicBool::new(false);
+        let custom: Arc<CustomPostFn> = Arc::new(move |_url, body, _verify| {
+            let encrypted_transmission_key = &body[..ENCRYPTED_TRANSMISSION_KEY_LEN];
+            let transmission_key = crypto::private_decrypt(encrypted_transmission_key, &server_key)
+                .expect("fake server could not unwrap the transmission key");
+
+            let mut response_json = serde_json::json!({
+                "records": [{"recordUid": uid_resp, "data": bytes_to_base64(&blob)}],
+            });
+            if !already_bound.swap(true, Ordering::SeqCst) {
+                response_json["encryptedAppKey"] = serde_json::json!(bytes_to_url_safe_str(&encrypted_app_key));
+            }
+            let encrypted_response =
+                crypto::encrypt_aes_gcm(&transmission_key, response_json.to_string().as_bytes()).unwrap();
+            Ok(KsmHttpResponse { status_code: 200, data: encrypted_response })
+        });
+
+        let config = Arc::new(InMemoryKeyValueStorage::new());
+        let options = ClientOptions {
+            token: Some(token),
+            hostname: Some("local.test".to_string()),
+            config,
+            custom_post_function: Some(custom),
+            ..ClientOptions::default()
+        };
+        (SecretsManager::new(options).unwrap(), uid)
+    }
+
+    #[test]
+    fn get_notation_resolves_title_and_type() {
+        let (sm, uid) =
+            bound_sm_with_record(serde_json::json!({"title": "My Login", "type": "login", "fields": [], "custom": []}));
+
+        assert_eq!(sm.get_notation(&format!("{uid}/title")).unwrap(), "My Login");
+        assert_eq!(sm.get_notation(&format!("keeper://{uid}/type")).unwrap(), "login");
+    }
+
+    #[test]
+    fn get_notation_returns_empty_string_for_a_null_notes_field() {
+        let (sm, uid) = bound_sm_with_record(serde_json::json!({
+            "title": "My Login", "type": "login", "fields": [], "custom": [], "notes": null,
+        }));
+
+        assert_eq!(sm.get_notation(&format!("{uid}/notes")).unwrap(), "");
+    }
+
+    #[test]
+    fn get_notation_returns_empty_string_when_notes_key_is_missing() {
+        let (sm, uid) =
+            bound_sm_with_record(serde_json::json!({"title": "My Login", "type": "login", "fields": [], "custom": []}));
+
+        assert_eq!(sm.get_notation(&format!("{uid}/notes")).unwrap(), "");
+    }
+
+    #[test]
+    fn get_notation_resolves_a_plain_notes_string() {
+        let (sm, uid) = bound_sm_with_record(serde_json::json!({
+            "title": "My Login", "type": "login", "fields": [], "custom": [], "notes": "remember to rotate this",
+        }));
+
+        assert_eq!(sm.get_notation(&format!("{uid}/notes")).unwrap(), "remember to rotate this");
+    }
+
+    #[test]
+    fn get_notation_resolves_a_standard_field() {
+        let (sm, uid) = bound_sm_with_record(serde_json::json!({
+            "title": "My Login",
+            "type": "login",
+            "fields": [{"type": "password", "value": ["s3cret"]}],
+            "custom": [],
+        }));
+
+        assert_eq!(sm.get_notation(&format!("{uid}/field/password")).unwrap(), "s3cret");
+    }
+
+    #[test]
+    fn get_notations_partial_keeps_each_notations_own_result() {
+        let (sm, uid) = bound_sm_with_record(serde_json::json!({
+            "title": "My Login",
+            "type": "login",
+            "fields": [{"type": "password", "value": ["s3cret"]}],
+            "custom": [],
+        }));
+
+        let notations = vec![
+            format!("{uid}/field/password"),
+            format!("{uid}/field/missing"),
+            format!("{uid}/bogus"),
+        ];
+        let results = sm.get_notations_partial(&notations);
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[&notations[0]].as_ref().unwrap(), "s3cret");
+        assert!(results[&notations[1]].is_err());
+        assert!(results[&notations[2]].is_err());
+    }
+
+    #[test]
+    fn get_notation_rejects_an_unknown_selector() {
+        let (sm, uid) =
+            bound_sm_with_record(serde_json::json!({"title": "My Login", "type": "login", "fields": [], "custom": []}));
+
+        assert!(sm.get_notation(&format!("{uid}/bogus")).is_err());
+    }
+
+    fn record_with_multivalued_url_field() -> serde_json::Value {
+        serde_json::json!({
+            "title": "My Login",
+            "type": "login",
+            "fields": [{"type": "url", "value": ["https://one.example", "https://two.example"]}],
+            "custom": [],
+        })
+    }
+
+    #[test]
+    fn get_notation_with_no_index_returns_the_first_value() {
+        let (sm, uid) = bound_sm_with_record(record_with_multivalued_url_field());
+
+        assert_eq!(sm.get_notation(&format!("{uid}/field/url")).unwrap(), "https://one.example");
+    }
+
+    #[test]
+    fn get_notation_with_an_explicit_index_returns_that_value() {
+        let (sm, uid) = bound_sm_with_record(record_with_multivalued_url_field());
+
+        assert_eq!(sm.get_notation(&format!("{uid}/field/url[1]")).unwrap(), "https://two.example");
+    }
+
+    #[test]
+    fn get_notation_with_an_out_of_range_index_errors() {
+        let (sm, uid) = bound_sm_with_record(record_with_multivalued_url_field());
+
+        assert!(sm.get_notation(&format!("{uid}/field/url[5]")).is_err());
+    }
+
+    #[test]
+    fn get_notation_with_an_empty_index_returns_a_json_array_of_every_value() {
+        let (sm, uid) = bound_sm_with_record(record_with_multivalued_url_field());
+
+        let result = sm.get_notation(&format!("{uid}/field/url[]")).unwrap();
+        assert_eq!(result, serde_json::json!(["https://one.example", "https://two.example"]).to_string());
+    }
+
+    #[test]
+    fn get_notation_results_with_an_empty_index_returns_every_value_as_a_vec() {
+        let (sm, uid) = bound_sm_with_record(record_with_multivalued_url_field());
+
+        let result = sm.get_notation_results(&format!("{uid}/field/url[]")).unwrap();
+        assert_eq!(result, vec!["https://one.example".to_string(), "https://two.example".to_string()]);
+    }
+
+    #[test]
+    fn get_notation_results_with_no_index_returns_a_single_element_vec() {
+        let (sm, uid) = bound_sm_with_record(record_with_multivalued_url_field());
+
+        let result = sm.get_notation_results(&format!("{uid}/field/url")).unwrap();
+        assert_eq!(result, vec!["https://one.example".to_string()]);
+    }
+
+    fn record_with_object_valued_name_field() -> serde_json::Value {
+        serde_json::json!({
+            "title": "My Login",
+            "type": "login",
+            "fields": [{
+                "type": "name",
+                "value": [
+                    {"first": "Alice", "last": "Anderson"},
+                    {"first": "Bob", "last": "Brown"},
+                ],
+            }],
+            "custom": [],
+        })
+    }
+
+    #[test]
+    fn get_notation_with_index_and_dictionary_key_selects_one_property_of_one_value() {
+        let (sm, uid) = bound_sm_with_record(record_with_object_valued_name_field());
+
+        assert_eq!(sm.get_notation(&format!("{uid}/field/name[0][first]")).unwrap(), "Alice");
+        assert_eq!(sm.get_notation(&format!("{uid}/field/name[1][last]")).unwrap(), "Brown");
+    }
+
+    #[test]
+    fn get_notation_with_empty_index_and_dictionary_key_selects_one_property_of_every_value() {
+        let (sm, uid) = bound_sm_with_record(record_with_object_valued_name_field());
+
+        let result = sm.get_notation(&format!("{uid}/field/name[][first]")).unwrap();
+        assert_eq!(result, serde_json::json!(["Alice", "Bob"]).to_string());
+
+        let results = sm.get_notation_results(&format!("{uid}/field/name[][first]")).unwrap();
+        assert_eq!(results, vec!["Alice".to_string(), "Bob".to_string()]);
+    }
+
+    #[test]
+    fn get_notation_with_no_index_and_dictionary_key_selects_one_property_of_the_first_value() {
+        let (sm, uid) = bound_sm_with_record(record_with_object_valued_name_field());
+
+        assert!(sm.get_notation(&format!("{uid}/field/name")).unwrap().contains("Alice"));
+    }
+
+    #[test]
+    fn get_notation_rejects_a_dictionary_key_on_a_value_that_is_not_an_object() {
+        let (sm, uid) = bound_sm_with_record(record_with_multivalued_url_field());
+
+        let err = sm.get_notation(&format!("{uid}/field/url[0][host]")).unwrap_err();
+        assert!(matches!(err, KSMRError::Other(_)));
+    }
+
+    #[test]
+    fn get_notation_rejects_a_dictionary_key_naming_a_property_that_is_not_present() {
+        let (sm, uid) = bound_sm_with_record(record_with_object_valued_name_field());
+
+        let err = sm.get_notation(&format!("{uid}/field/name[0][middle]")).unwrap_err();
+        assert!(matches!(err, KSMRError::Other(_)));
+    }
+
+    #[test]
+    fn get_notation_rejects_an_empty_dictionary_key_predicate() {
+        let (sm, uid) = bound_sm_with_record(record_with_object_valued_name_field());
+
+        assert!(sm.get_notation(&format!("{uid}/field/name[0][]")).is_err());
+    }
+
+    #[test]
+    fn get_notation_rejects_a_third_index_predicate() {
+        let (sm, uid) = bound_sm_with_record(record_with_object_valued_name_field());
+
+        assert!(sm.get_notation(&format!("{uid}/field/name[0][first][extra]")).is_err());
+    }
+
+    #[test]
+    fn get_notation_rejects_text_between_index_predicates() {
+        let (sm, uid) = bound_sm_with_record(record_with_object_valued_name_field());
+
+        assert!(sm.get_notation(&format!("{uid}/field/name[0]x[first]")).is_err());
+    }
+
+    #[test]
+    fn get_notation_reports_the_position_of_a_missing_selector() {
+        let (sm, uid) =
+            bound_sm_with_record(serde_json::json!({"title": "My Login", "type": "login", "fields": [], "custom": []}));
+
+        let notation = format!("keeper://{uid}");
+        let err = sm.get_notation(&notation).unwrap_err();
+        match err {
+            KSMRError::Notation { position, notation: reported, .. } => {
+                assert_eq!(position, notation.len());
+                assert_eq!(reported, notation);
+            }
+            other => panic!("expected a Notation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn get_notation_reports_the_position_of_an_unterminated_index() {
+        let (sm, uid) = bound_sm_with_record(record_with_multivalued_url_field());
+
+        let notation = format!("{uid}/field/url[0");
+        let err = sm.get_notation(&notation).unwrap_err();
+        let key_pos = notation.find("url[0").unwrap();
+        match err {
+            KSMRError::Notation { position, .. } => assert_eq!(position, key_pos),
+            other => panic!("expected a Notation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn get_notation_reports_the_position_of_a_non_numeric_index() {
+        let (sm, uid) = bound_sm_with_record(record_with_multivalued_url_field());
+
+        let notation = format!("{uid}/field/url[x]");
+        let err = sm.get_notation(&notation).unwrap_err();
+        // `rfind('[') + 1`, not `find('x')` - the random uid can itself contain an 'x'.
+        let index_pos = notation.rfind('[').unwrap() + 1;
+        match err {
+            KSMRError::Notation { position, .. } => assert_eq!(position, index_pos),
+            other => panic!("expected a Notation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn get_notation_reports_the_position_of_a_missing_uid() {
+        let (sm, _uid) =
+            bound_sm_with_record(serde_json::json!({"title": "My Login", "type": "login", "fields": [], "custom": []}));
+
+        let err = sm.get_notation("keeper:///title").unwrap_err();
+        match err {
+            KSMRError::Notation { position, .. } => assert_eq!(position, "keeper://".len()),
+            other => panic!("expected a Notation error, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,272 @@
+//! Wire payload shapes exchanged with the Secrets Manager gateway.
+//! Mirrors `dto/payload.py` in the Python core SDK.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetPayload {
+    #[serde(rename = "clientVersion")]
+    pub client_version: String,
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "publicKey", skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    #[serde(rename = "requestedRecords", skip_serializing_if = "Option::is_none")]
+    pub requested_records: Option<Vec<String>>,
+}
+
+/// Selects how the server should apply an `update_secret` call, mirroring the
+/// two-phase commit Keeper uses internally for safe credential rotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateTransactionType {
+    /// Apply the update immediately (the default when omitted).
+    General,
+    /// Stage the update so it can be rolled back if the caller's downstream
+    /// provisioning step fails.
+    Rollback,
+}
+
+impl UpdateTransactionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpdateTransactionType::General => "general",
+            UpdateTransactionType::Rollback => "rollback",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdatePayload {
+    #[serde(rename = "clientVersion")]
+    pub client_version: String,
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "recordUid")]
+    pub record_uid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<i64>,
+    pub data: String,
+    #[serde(rename = "transactionType", default, skip_serializing_if = "Option::is_none")]
+    pub transaction_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePayload {
+    #[serde(rename = "clientVersion")]
+    pub client_version: String,
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "recordUid")]
+    pub record_uid: String,
+    #[serde(rename = "recordKey")]
+    pub record_key: String,
+    #[serde(rename = "folderUid")]
+    pub folder_uid: String,
+    #[serde(rename = "folderKey")]
+    pub folder_key: String,
+    pub data: String,
+}
+
+/// Shape of [`CreatePayload::data`] (before encryption) for
+/// [`crate::client::SecretsManager::reencrypt_record_for`]. Deliberately
+/// narrower than [`crate::dto::Record`]: it carries only the fields that
+/// describe *what the record is* (`type`/`title`/`fields`/`custom`/`notes`),
+/// not the source vault's own bookkeeping (`uid`, `folder_uid`, `revision`) -
+/// a cross-application migration shouldn't leak the source record's
+/// identity or location into the destination's encrypted data.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReencryptedRecordData {
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub title: String,
+    #[serde(default)]
+    pub fields: Vec<crate::dto::RecordField>,
+    #[serde(default)]
+    pub custom: Vec<crate::dto::RecordField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<serde_json::Value>,
+}
+
+/// Request body for the `add_file` endpoint, which registers a new file
+/// record (and the owner record's updated `fileRef`) before the encrypted
+/// file bytes are uploaded to the URL returned in [`AddFileResponse`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileUploadPayload {
+    #[serde(rename = "clientVersion")]
+    pub client_version: String,
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "fileRecordUid")]
+    pub file_record_uid: String,
+    #[serde(rename = "fileRecordKey")]
+    pub file_record_key: String,
+    #[serde(rename = "fileRecordData")]
+    pub file_record_data: String,
+    #[serde(rename = "ownerRecordUid")]
+    pub owner_record_uid: String,
+    #[serde(rename = "ownerRecordData")]
+    pub owner_record_data: String,
+    #[serde(rename = "linkKey")]
+    pub link_key: String,
+    #[serde(rename = "fileSize")]
+    pub file_size: usize,
+}
+
+/// The plaintext metadata encrypted under a file's own key, mirroring
+/// `KeeperFileData` in the Python core SDK.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileRecordMeta {
+    pub name: String,
+    pub size: usize,
+    pub title: String,
+    #[serde(rename = "lastModified")]
+    pub last_modified: u64,
+    #[serde(rename = "type")]
+    pub mime_type: String,
+}
+
+/// [`FileRecordMeta`] plus the uid identifying which file record it
+/// describes - the shape [`crate::client::SecretsManager::list_attachments`]
+/// hands back, so a caller can show a file's name, size and type without
+/// ever decrypting its content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentInfo {
+    pub uid: String,
+    pub name: String,
+    pub size: usize,
+    pub title: String,
+    #[serde(rename = "lastModified")]
+    pub last_modified: u64,
+    #[serde(rename = "type")]
+    pub mime_type: String,
+}
+
+/// Decoded response to an `add_file` call: where to upload the encrypted
+/// file bytes, and the form fields the upload must be submitted with.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AddFileResponse {
+    pub url: String,
+    pub parameters: String,
+    #[serde(rename = "successStatusCode", default)]
+    pub success_status_code: Option<u16>,
+}
+
+/// Request body for the `request_download` endpoint, the download-side
+/// counterpart to `add_file`: given a file record's uid, returns a presigned
+/// URL the encrypted file bytes can be read back from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestDownloadPayload {
+    #[serde(rename = "clientVersion")]
+    pub client_version: String,
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "fileRecordUid")]
+    pub file_record_uid: String,
+}
+
+/// Decoded response to a `request_download` call.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RequestDownloadResponse {
+    pub url: String,
+}
+
+/// Plaintext payload plus its detached signature, ready to be sent over the wire.
+pub struct EncryptedPayload {
+    pub encrypted_payload: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// Result of a single call to [`crate::client::SecretsManager::post_query`].
+#[derive(Debug, Clone)]
+pub struct KsmHttpResponse {
+    pub status_code: u16,
+    pub data: Vec<u8>,
+}
+
+/// A still-encrypted record as returned inside a `get_secret` response, either
+/// top-level or nested under a [`WireFolder`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct WireRecord {
+    #[serde(rename = "recordUid")]
+    pub record_uid: String,
+    #[serde(rename = "recordKey", default)]
+    pub record_key: Option<String>,
+    pub data: String,
+    #[serde(default)]
+    pub revision: Option<i64>,
+    #[serde(rename = "isEditable", default)]
+    pub is_editable: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WireFolder {
+    #[serde(rename = "folderUid")]
+    pub folder_uid: String,
+    #[serde(rename = "folderKey")]
+    pub folder_key: String,
+    /// AES-256-GCM-encrypted `{"name": ...}` blob, present for folders created
+    /// through [`crate::client::SecretsManager::create_folder`]. Shared folders
+    /// the gateway surfaces for other reasons don't carry this, so it's left
+    /// `None` and their name stays unresolved.
+    #[serde(default)]
+    pub data: Option<String>,
+    /// Uid of the folder this one was created under, present for folders
+    /// created through [`crate::client::SecretsManager::create_folder`].
+    #[serde(rename = "parentUid", default)]
+    pub parent_uid: Option<String>,
+    #[serde(default)]
+    pub records: Vec<WireRecord>,
+}
+
+/// Request body for the `create_folder` endpoint, which creates a new folder
+/// under a shared folder or another folder the application already has a key
+/// for. Mirrors [`CreatePayload`]'s shape for records.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateFolderPayload {
+    #[serde(rename = "clientVersion")]
+    pub client_version: String,
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "folderUid")]
+    pub folder_uid: String,
+    #[serde(rename = "sharedFolderUid")]
+    pub parent_uid: String,
+    #[serde(rename = "sharedFolderKey")]
+    pub parent_key: String,
+    pub data: String,
+}
+
+/// Request body for the `update_folder` endpoint, which renames (or
+/// otherwise updates the metadata of) a folder this application already
+/// holds a key for. Mirrors [`CreateFolderPayload`]'s shape, minus the
+/// parent/key fields a rename doesn't touch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateFolderPayload {
+    #[serde(rename = "clientVersion")]
+    pub client_version: String,
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "folderUid")]
+    pub folder_uid: String,
+    pub data: String,
+}
+
+/// The decrypted JSON body of a `get_secret` response, before records/folders
+/// have had their own, separately-encrypted keys and data unwrapped.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GetSecretsResponseWire {
+    #[serde(rename = "encryptedAppKey", default)]
+    pub encrypted_app_key: Option<String>,
+    #[serde(rename = "appOwnerPublicKey", default)]
+    pub app_owner_public_key: Option<String>,
+    #[serde(default)]
+    pub records: Vec<WireRecord>,
+    #[serde(default)]
+    pub folders: Vec<WireFolder>,
+    #[serde(rename = "appData", default)]
+    pub app_data: Option<String>,
+    #[serde(rename = "expiresOn", default)]
+    pub expires_on: Option<i64>,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
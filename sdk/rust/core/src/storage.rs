@@ -0,0 +1,575 @@
+//! Key/value configuration storage, mirroring `storage.py` in the Python core SDK.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::KSMRError;
+
+/// Default location for the JSON config file, relative to the current directory,
+/// used when [`FileKeyValueStorage::new`] is given neither a path nor `KSM_CONFIG_FILE`.
+const DEFAULT_CONFIG_FILE_LOCATION: &str = "client-config.json";
+
+/// Well-known configuration keys persisted across SDK runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigKey {
+    Url,
+    ClientId,
+    ClientKey,
+    AppKey,
+    OwnerPublicKey,
+    PrivateKey,
+    ServerPublicKeyId,
+    BindingToken,
+    BindingKey,
+    Hostname,
+}
+
+impl ConfigKey {
+    /// Every variant, in declaration order. Used by
+    /// [`KeyValueStorage::present_keys`] to check each key without a caller
+    /// having to enumerate them by hand.
+    pub const ALL: &'static [ConfigKey] = &[
+        ConfigKey::Url,
+        ConfigKey::ClientId,
+        ConfigKey::ClientKey,
+        ConfigKey::AppKey,
+        ConfigKey::OwnerPublicKey,
+        ConfigKey::PrivateKey,
+        ConfigKey::ServerPublicKeyId,
+        ConfigKey::BindingToken,
+        ConfigKey::BindingKey,
+        ConfigKey::Hostname,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigKey::Url => "url",
+            ConfigKey::ClientId => "clientId",
+            ConfigKey::ClientKey => "clientKey",
+            ConfigKey::AppKey => "appKey",
+            ConfigKey::OwnerPublicKey => "appOwnerPublicKey",
+            ConfigKey::PrivateKey => "privateKey",
+            ConfigKey::ServerPublicKeyId => "serverPublicKeyId",
+            ConfigKey::BindingToken => "bat",
+            ConfigKey::BindingKey => "bindingKey",
+            ConfigKey::Hostname => "hostname",
+        }
+    }
+}
+
+/// Interface for pluggable key/value configuration storage.
+pub trait KeyValueStorage: Send + Sync {
+    fn get(&self, key: ConfigKey) -> Option<String>;
+    fn set(&self, key: ConfigKey, value: String);
+    fn delete(&self, key: ConfigKey);
+    fn delete_all(&self);
+    fn contains(&self, key: ConfigKey) -> bool {
+        self.get(key).is_some()
+    }
+    fn is_empty(&self) -> bool;
+    /// Whether this config holds an unwrapped application key, i.e. the
+    /// one-time token has already been exchanged with the gateway. A config
+    /// with only a token (`ConfigKey::ClientKey`) and no `ConfigKey::AppKey`
+    /// is unbound.
+    fn is_bound(&self) -> bool {
+        self.contains(ConfigKey::AppKey)
+    }
+    /// Returns every [`ConfigKey`] this storage currently holds a value for,
+    /// without returning the values themselves - useful for diagnostics
+    /// (e.g. a `ksm config keys` command) that need to confirm a config is
+    /// populated the way they expect without risking a secret value ending
+    /// up in a log line.
+    fn present_keys(&self) -> Vec<ConfigKey> {
+        ConfigKey::ALL.iter().copied().filter(|&key| self.contains(key)).collect()
+    }
+    /// Returns the bound application's client id, or `None` if this config
+    /// hasn't bound yet. A clean accessor for the common "does this config
+    /// belong to the application I expect" check (e.g. a fleet-management
+    /// tool confirming every host got the right config), so a caller doesn't
+    /// have to reach past this trait into `ConfigKey::ClientId` for
+    /// something this common.
+    ///
+    /// Unlike `ConfigKey::AppKey` or `ConfigKey::PrivateKey`, the client id
+    /// is a one-way HMAC of the one-time token (see
+    /// [`crate::client::SecretsManager::bind_if_needed`]), not something an
+    /// attacker could use to derive the private key or app key back out of -
+    /// it identifies the application without granting access to it, so it's
+    /// safe to log or compare in the clear.
+    fn client_id(&self) -> Option<String> {
+        self.get(ConfigKey::ClientId)
+    }
+
+    /// Serializes every [`ConfigKey`] this storage currently holds into the
+    /// same JSON-then-base64 blob [`InMemoryKeyValueStorage::from_base64`]
+    /// reads back (the `KSM_CONFIG` format), then wraps it as a
+    /// copy-paste-ready `export KSM_CONFIG=...` shell line - for a user
+    /// moving a bound client from a file-based config to the environment
+    /// variable, without reaching for a one-off script in another language
+    /// to produce the blob.
+    fn to_env_export(&self) -> Result<String, KSMRError> {
+        let map: HashMap<&'static str, String> = self
+            .present_keys()
+            .into_iter()
+            .filter_map(|key| self.get(key).map(|value| (key.as_str(), value)))
+            .collect();
+        let json = serde_json::to_string(&map).map_err(|e| KSMRError::Serialization(e.to_string()))?;
+        Ok(format!("export KSM_CONFIG={}", crate::utils::bytes_to_base64(json.as_bytes())))
+    }
+}
+
+/// In-memory implementation of [`KeyValueStorage`], primarily used in tests
+/// and for the `KSM_CONFIG` environment variable base64 blob.
+#[derive(Default)]
+pub struct InMemoryKeyValueStorage {
+    values: Mutex<HashMap<String, String>>,
+}
+
+impl InMemoryKeyValueStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds storage from a base64-encoded JSON config blob (the format of `KSM_CONFIG`).
+    pub fn from_base64(config_base64: &str) -> Result<Self, KSMRError> {
+        let json_bytes = crate::utils::base64_to_bytes(config_base64)?;
+        let json_str = crate::utils::bytes_to_string(&json_bytes)?;
+        let map: HashMap<String, String> = serde_json::from_str(&json_str)
+            .map_err(|e| KSMRError::Config(format!("invalid KSM_CONFIG blob: {e}")))?;
+        Ok(Self { values: Mutex::new(map) })
+    }
+
+    /// Builds storage by reading all of `r` and auto-detecting whether it
+    /// holds a raw JSON config object or a base64-encoded blob (the
+    /// `KSM_CONFIG` format). Lets callers pipe a config in over stdin (e.g. a
+    /// `docker secret`) instead of writing it to disk or an env var first.
+    pub fn from_reader(mut r: impl Read) -> Result<Self, KSMRError> {
+        let mut raw = String::new();
+        r.read_to_string(&mut raw)
+            .map_err(|e| KSMRError::Config(format!("could not read config: {e}")))?;
+        let trimmed = raw.trim();
+
+        if trimmed.starts_with('{') {
+            let map: HashMap<String, String> = serde_json::from_str(trimmed)
+                .map_err(|e| KSMRError::Config(format!("config read from reader may contain JSON format problems: {e}")))?;
+            Ok(Self { values: Mutex::new(map) })
+        } else {
+            Self::from_base64(trimmed)
+        }
+    }
+}
+
+impl KeyValueStorage for InMemoryKeyValueStorage {
+    fn get(&self, key: ConfigKey) -> Option<String> {
+        self.values.lock().unwrap().get(key.as_str()).cloned()
+    }
+
+    fn set(&self, key: ConfigKey, value: String) {
+        self.values.lock().unwrap().insert(key.as_str().to_string(), value);
+    }
+
+    fn delete(&self, key: ConfigKey) {
+        self.values.lock().unwrap().remove(key.as_str());
+    }
+
+    fn delete_all(&self) {
+        self.values.lock().unwrap().clear();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.values.lock().unwrap().is_empty()
+    }
+}
+
+/// Result of [`FileKeyValueStorage::check_permissions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionStatus {
+    /// The config file is not readable or writable by anyone but its owner.
+    Secure,
+    /// The config file's permission bits grant group and/or other some
+    /// access (Unix `st_mode` has any of `0o077` set) - the one-time token
+    /// it was bound with and its private key are readable by more than the
+    /// owning user.
+    TooPermissive {
+        /// The file's raw Unix permission bits, for a caller that wants to
+        /// report the exact mode (e.g. `ksm doctor` printing `0640`).
+        mode: u32,
+    },
+    /// This platform has no permission model this check understands. Unix
+    /// file mode bits are the only one this crate inspects; a Windows DACL
+    /// would need a Windows-specific dependency this crate doesn't carry,
+    /// so [`FileKeyValueStorage::check_permissions`] can't report anything
+    /// meaningful there yet.
+    NotApplicable,
+}
+
+/// File-based implementation of [`KeyValueStorage`] that persists the config
+/// as a JSON object on disk, matching `FileKeyValueStorage` in the Python core SDK.
+///
+/// Unlike the Python implementation, missing parent directories are created
+/// automatically (with `0700` permissions on Unix) the first time the config
+/// file needs to be written, instead of failing obscurely on first run.
+pub struct FileKeyValueStorage {
+    config_file_location: PathBuf,
+    cache: Mutex<HashMap<String, String>>,
+}
+
+impl FileKeyValueStorage {
+    /// Opens (or prepares to create) the config file at `config_file_location`,
+    /// falling back to the `KSM_CONFIG_FILE` environment variable and then to
+    /// [`DEFAULT_CONFIG_FILE_LOCATION`] when no path is given.
+    pub fn new(config_file_location: Option<PathBuf>) -> Result<Self, KSMRError> {
+        let path = config_file_location
+            .or_else(|| std::env::var("KSM_CONFIG_FILE").ok().map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_FILE_LOCATION));
+
+        let storage = Self { config_file_location: path, cache: Mutex::new(HashMap::new()) };
+        storage.create_config_file_if_missing()?;
+        storage.load()?;
+        Ok(storage)
+    }
+
+    /// Opens (or prepares to create) `filename` under `dir`, bypassing the
+    /// `KSM_CONFIG_FILE`/[`DEFAULT_CONFIG_FILE_LOCATION`] fallback chain
+    /// entirely - useful for a multi-tenant process that wants every
+    /// tenant's config isolated under its own chosen directory rather than
+    /// juggling working directories or the environment variable to keep
+    /// them apart.
+    pub fn with_base_dir(dir: &Path, filename: &str) -> Result<Self, KSMRError> {
+        Self::new(Some(dir.join(filename)))
+    }
+
+    /// Creates the parent directory tree (with `0700` permissions on Unix) and
+    /// an empty `{}` config file (with `0600` permissions on Unix) if nothing
+    /// exists there yet. Returns a [`KSMRError::Config`] naming the directory
+    /// or file if it cannot be created.
+    fn create_config_file_if_missing(&self) -> Result<(), KSMRError> {
+        if self.config_file_location.exists() {
+            return Ok(());
+        }
+
+        if let Some(parent) = self.config_file_location.parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    KSMRError::Config(format!(
+                        "could not create config directory {}: {e}",
+                        parent.display()
+                    ))
+                })?;
+                set_secure_dir_permissions(parent)?;
+            }
+        }
+
+        create_config_file_with_secure_permissions(&self.config_file_location, "{}")
+    }
+
+    fn load(&self) -> Result<(), KSMRError> {
+        let contents = std::fs::read_to_string(&self.config_file_location).map_err(|e| {
+            KSMRError::Config(format!(
+                "could not read config file {}: {e}",
+                self.config_file_location.display()
+            ))
+        })?;
+        let map: HashMap<String, String> = if contents.trim().is_empty() {
+            HashMap::new()
+        } else {
+            serde_json::from_str(&contents).map_err(|e| {
+                KSMRError::Config(format!(
+                    "{} may contain JSON format problems: {e}",
+                    self.config_file_location.display()
+                ))
+            })?
+        };
+        *self.cache.lock().unwrap() = map;
+        Ok(())
+    }
+
+    /// Reports whether the config file's on-disk permissions still match the
+    /// `0700`-directory, owner-only intent [`FileKeyValueStorage::new`]
+    /// sets up - useful for a `ksm doctor`-style command to warn that an
+    /// external edit (an `scp`, an archive extraction, a backup restore)
+    /// left the one-time token and private key it contains readable by
+    /// group or other. See [`PermissionStatus`] for what each outcome means,
+    /// including why this can't inspect anything on non-Unix platforms yet.
+    pub fn check_permissions(&self) -> Result<PermissionStatus, KSMRError> {
+        let metadata = std::fs::metadata(&self.config_file_location).map_err(|e| {
+            KSMRError::Config(format!(
+                "could not stat config file {}: {e}",
+                self.config_file_location.display()
+            ))
+        })?;
+        Ok(file_permission_status(&metadata))
+    }
+
+    fn save(&self) -> Result<(), KSMRError> {
+        let map = self.cache.lock().unwrap();
+        let json = serde_json::to_string_pretty(&*map)
+            .map_err(|e| KSMRError::Config(format!("could not serialize config: {e}")))?;
+        std::fs::write(&self.config_file_location, json).map_err(|e| {
+            KSMRError::Config(format!(
+                "could not write config file {}: {e}",
+                self.config_file_location.display()
+            ))
+        })
+    }
+}
+
+impl KeyValueStorage for FileKeyValueStorage {
+    fn get(&self, key: ConfigKey) -> Option<String> {
+        self.cache.lock().unwrap().get(key.as_str()).cloned()
+    }
+
+    fn set(&self, key: ConfigKey, value: String) {
+        self.cache.lock().unwrap().insert(key.as_str().to_string(), value);
+        let _ = self.save();
+    }
+
+    fn delete(&self, key: ConfigKey) {
+        self.cache.lock().unwrap().remove(key.as_str());
+        let _ = self.save();
+    }
+
+    fn delete_all(&self) {
+        self.cache.lock().unwrap().clear();
+        let _ = self.save();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.cache.lock().unwrap().is_empty()
+    }
+}
+
+#[cfg(unix)]
+fn set_secure_dir_permissions(dir: &Path) -> Result<(), KSMRError> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700)).map_err(|e| {
+        KSMRError::Config(format!("could not set permissions on {}: {e}", dir.display()))
+    })
+}
+
+#[cfg(not(unix))]
+fn set_secure_dir_permissions(_dir: &Path) -> Result<(), KSMRError> {
+    Ok(())
+}
+
+/// Creates `path` with `contents`, `0600`-only on Unix from the moment the file
+/// first exists. Unlike a plain `std::fs::write` followed by a `chmod`, there is
+/// no window where the file is briefly readable under the umask-derived default
+/// mode (typically `0644`/`0664`) before permissions get tightened - important
+/// since this file holds the one-time token and private key
+/// [`FileKeyValueStorage::check_permissions`] is meant to be guarding.
+#[cfg(unix)]
+fn create_config_file_with_secure_permissions(path: &Path, contents: &str) -> Result<(), KSMRError> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .map_err(|e| KSMRError::Config(format!("could not create config file {}: {e}", path.display())))?;
+    file.write_all(contents.as_bytes())
+        .map_err(|e| KSMRError::Config(format!("could not create config file {}: {e}", path.display())))
+}
+
+#[cfg(not(unix))]
+fn create_config_file_with_secure_permissions(path: &Path, contents: &str) -> Result<(), KSMRError> {
+    std::fs::write(path, contents)
+        .map_err(|e| KSMRError::Config(format!("could not create config file {}: {e}", path.display())))
+}
+
+#[cfg(unix)]
+fn file_permission_status(metadata: &std::fs::Metadata) -> PermissionStatus {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = metadata.permissions().mode();
+    if mode & 0o077 != 0 {
+        PermissionStatus::TooPermissive { mode: mode & 0o777 }
+    } else {
+        PermissionStatus::Secure
+    }
+}
+
+#[cfg(not(unix))]
+fn file_permission_status(_metadata: &std::fs::Metadata) -> PermissionStatus {
+    PermissionStatus::NotApplicable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_memory_storage_round_trips_values() {
+        let storage = InMemoryKeyValueStorage::new();
+        assert!(storage.is_empty());
+        storage.set(ConfigKey::ClientKey, "token".to_string());
+        assert_eq!(storage.get(ConfigKey::ClientKey), Some("token".to_string()));
+        assert!(storage.contains(ConfigKey::ClientKey));
+        storage.delete(ConfigKey::ClientKey);
+        assert!(!storage.contains(ConfigKey::ClientKey));
+    }
+
+    #[test]
+    fn is_bound_reflects_whether_an_app_key_is_present() {
+        let storage = InMemoryKeyValueStorage::new();
+        assert!(!storage.is_bound());
+        storage.set(ConfigKey::ClientKey, "token".to_string());
+        assert!(!storage.is_bound());
+        storage.set(ConfigKey::AppKey, "app-key".to_string());
+        assert!(storage.is_bound());
+    }
+
+    #[test]
+    fn client_id_returns_none_before_binding_and_the_value_after() {
+        let storage = InMemoryKeyValueStorage::new();
+        assert_eq!(storage.client_id(), None);
+
+        storage.set(ConfigKey::ClientId, "client-id-abc".to_string());
+        assert_eq!(storage.client_id(), Some("client-id-abc".to_string()));
+    }
+
+    #[test]
+    fn present_keys_lists_only_the_keys_that_have_values() {
+        let storage = InMemoryKeyValueStorage::new();
+        assert!(storage.present_keys().is_empty());
+
+        storage.set(ConfigKey::ClientId, "client-id".to_string());
+        storage.set(ConfigKey::OwnerPublicKey, "owner-public-key".to_string());
+
+        let present = storage.present_keys();
+        assert_eq!(present.len(), 2);
+        assert!(present.contains(&ConfigKey::ClientId));
+        assert!(present.contains(&ConfigKey::OwnerPublicKey));
+        assert!(!present.contains(&ConfigKey::PrivateKey));
+    }
+
+    #[test]
+    fn from_reader_detects_raw_json_config() {
+        let storage = InMemoryKeyValueStorage::from_reader("{\"clientId\":\"abc\"}".as_bytes()).unwrap();
+        assert_eq!(storage.get(ConfigKey::ClientId), Some("abc".to_string()));
+    }
+
+    #[test]
+    fn from_reader_detects_base64_config() {
+        let base64 = crate::utils::bytes_to_base64(b"{\"clientId\":\"abc\"}");
+        let storage = InMemoryKeyValueStorage::from_reader(base64.as_bytes()).unwrap();
+        assert_eq!(storage.get(ConfigKey::ClientId), Some("abc".to_string()));
+    }
+
+    #[test]
+    fn to_env_export_round_trips_through_from_base64() {
+        let storage = InMemoryKeyValueStorage::new();
+        storage.set(ConfigKey::ClientId, "client-id-abc".to_string());
+        storage.set(ConfigKey::AppKey, "app-key-xyz".to_string());
+
+        let export_line = storage.to_env_export().unwrap();
+        let prefix = "export KSM_CONFIG=";
+        assert!(export_line.starts_with(prefix), "unexpected export line: {export_line}");
+
+        let reopened = InMemoryKeyValueStorage::from_base64(&export_line[prefix.len()..]).unwrap();
+        assert_eq!(reopened.get(ConfigKey::ClientId), Some("client-id-abc".to_string()));
+        assert_eq!(reopened.get(ConfigKey::AppKey), Some("app-key-xyz".to_string()));
+    }
+
+    #[test]
+    fn to_env_export_omits_unset_keys() {
+        let storage = InMemoryKeyValueStorage::new();
+        storage.set(ConfigKey::ClientId, "client-id-abc".to_string());
+
+        let export_line = storage.to_env_export().unwrap();
+        let prefix = "export KSM_CONFIG=";
+        let reopened = InMemoryKeyValueStorage::from_base64(&export_line[prefix.len()..]).unwrap();
+        assert_eq!(reopened.present_keys(), vec![ConfigKey::ClientId]);
+    }
+
+    #[test]
+    fn file_storage_creates_missing_nested_directories() {
+        let dir = std::env::temp_dir().join(format!(
+            "ksm-test-{}",
+            crate::utils::generate_uid()
+        ));
+        let config_path = dir.join("nested").join("config.json");
+        assert!(!dir.exists());
+
+        let storage = FileKeyValueStorage::new(Some(config_path.clone())).unwrap();
+        assert!(config_path.exists());
+        storage.set(ConfigKey::ClientId, "abc".to_string());
+
+        let reopened = FileKeyValueStorage::new(Some(config_path.clone())).unwrap();
+        assert_eq!(reopened.get(ConfigKey::ClientId), Some("abc".to_string()));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn with_base_dir_isolates_a_tenants_config_under_its_own_directory() {
+        let base = std::env::temp_dir().join(format!(
+            "ksm-test-{}",
+            crate::utils::generate_uid()
+        ));
+
+        let tenant_a = FileKeyValueStorage::with_base_dir(&base, "tenant-a.json").unwrap();
+        tenant_a.set(ConfigKey::ClientId, "a".to_string());
+        let tenant_b = FileKeyValueStorage::with_base_dir(&base, "tenant-b.json").unwrap();
+        tenant_b.set(ConfigKey::ClientId, "b".to_string());
+
+        assert_eq!(tenant_a.get(ConfigKey::ClientId), Some("a".to_string()));
+        assert_eq!(tenant_b.get(ConfigKey::ClientId), Some("b".to_string()));
+        assert!(base.join("tenant-a.json").exists());
+        assert!(base.join("tenant-b.json").exists());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_storage_sets_secure_directory_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!(
+            "ksm-test-{}",
+            crate::utils::generate_uid()
+        ));
+        let config_path = dir.join("config.json");
+
+        let _storage = FileKeyValueStorage::new(Some(config_path)).unwrap();
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o700);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn check_permissions_is_secure_by_default() {
+        let dir = std::env::temp_dir().join(format!(
+            "ksm-test-{}",
+            crate::utils::generate_uid()
+        ));
+        let config_path = dir.join("config.json");
+
+        let storage = FileKeyValueStorage::new(Some(config_path)).unwrap();
+        assert_eq!(storage.check_permissions().unwrap(), PermissionStatus::Secure);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn check_permissions_flags_a_world_readable_config_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!(
+            "ksm-test-{}",
+            crate::utils::generate_uid()
+        ));
+        let config_path = dir.join("config.json");
+
+        let storage = FileKeyValueStorage::new(Some(config_path.clone())).unwrap();
+        std::fs::set_permissions(&config_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(storage.check_permissions().unwrap(), PermissionStatus::TooPermissive { mode: 0o644 });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
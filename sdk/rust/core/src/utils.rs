@@ -0,0 +1,580 @@
+//! General-purpose helpers: byte/string/base64 conversions and UID generation.
+//! Mirrors `utils.py` in the Python core SDK.
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+
+use crate::crypto::{generate_random_bytes, generate_random_bytes_with, OsRngProvider, RngProvider};
+use crate::error::KSMRError;
+
+/// Number of bytes shown either side of the first invalid byte in the hex
+/// preview emitted by [`bytes_to_string`]'s error.
+const UTF8_ERROR_PREVIEW_RADIUS: usize = 8;
+
+/// Decodes `b` as UTF-8, or fails with a [`KSMRError::Serialization`] carrying
+/// a hex preview around the offending byte and a hint that this usually means
+/// the data was decrypted with the wrong key (a stale app key, most commonly).
+pub fn bytes_to_string(b: &[u8]) -> Result<String, KSMRError> {
+    String::from_utf8(b.to_vec()).map_err(|e| {
+        let bad_index = e.utf8_error().valid_up_to();
+        let start = bad_index.saturating_sub(UTF8_ERROR_PREVIEW_RADIUS);
+        let end = (bad_index + UTF8_ERROR_PREVIEW_RADIUS).min(b.len());
+        let preview: String = b[start..end].iter().map(|byte| format!("{byte:02x}")).collect();
+        KSMRError::Serialization(format!(
+            "decrypted data is not valid UTF-8 at byte {bad_index} (bytes {start}..{end}: {preview}); \
+             this usually indicates a key mismatch (e.g. a stale app key)"
+        ))
+    })
+}
+
+/// Like [`bytes_to_string`], but never fails: invalid UTF-8 sequences are
+/// replaced with U+FFFD instead of erroring. Meant for legacy records whose
+/// decrypted bytes contain stray non-UTF-8 bytes in a single field - using
+/// this instead of [`bytes_to_string`] keeps that record (and the rest of a
+/// batch fetched alongside it) readable, at the cost of silently mangling
+/// the corrupted bytes rather than surfacing the problem.
+pub fn bytes_to_string_lossy(b: &[u8]) -> String {
+    String::from_utf8_lossy(b).into_owned()
+}
+
+pub fn string_to_bytes(s: &str) -> Vec<u8> {
+    s.as_bytes().to_vec()
+}
+
+pub fn bytes_to_base64(b: &[u8]) -> String {
+    STANDARD.encode(b)
+}
+
+pub fn base64_to_bytes(s: &str) -> Result<Vec<u8>, KSMRError> {
+    STANDARD
+        .decode(s)
+        .map_err(|e| KSMRError::Serialization(format!("invalid base64: {e}")))
+}
+
+pub fn bytes_to_url_safe_str(b: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(b)
+}
+
+pub fn url_safe_str_to_bytes(s: &str) -> Result<Vec<u8>, KSMRError> {
+    URL_SAFE_NO_PAD
+        .decode(s)
+        .map_err(|e| KSMRError::Serialization(format!("invalid base64url: {e}")))
+}
+
+/// Encodes `b` as lowercase hex, with no `0x` prefix.
+pub fn bytes_to_hex(b: &[u8]) -> String {
+    b.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Decodes `s` as hex, case-insensitively and tolerating an optional `0x`/`0X`
+/// prefix. Fails with a [`KSMRError::Serialization`] on an odd-length string
+/// or a non-hex character.
+pub fn hex_to_bytes(s: &str) -> Result<Vec<u8>, KSMRError> {
+    let s = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+    if !s.len().is_multiple_of(2) {
+        return Err(KSMRError::Serialization(format!("invalid hex: odd-length string ({} chars)", s.len())));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|e| KSMRError::Serialization(format!("invalid hex: {e}"))))
+        .collect()
+}
+
+/// Generates a record/folder UID: 16 random bytes, URL-safe base64 encoded
+/// (no padding), using the given randomness source.
+pub fn generate_uid_with(rng: &dyn RngProvider) -> String {
+    bytes_to_url_safe_str(&generate_random_bytes_with(rng, 16))
+}
+
+/// Generates a record/folder UID using the OS CSPRNG.
+pub fn generate_uid() -> String {
+    generate_uid_with(&OsRngProvider)
+}
+
+/// Encodes 16 raw UID bytes as the URL-safe base64 string form used
+/// throughout the SDK, the inverse of [`uid_to_bytes`].
+pub fn uid_from_bytes(bytes: &[u8; 16]) -> String {
+    bytes_to_url_safe_str(bytes)
+}
+
+/// Decodes a UID string back to its 16 raw bytes, failing with a
+/// [`KSMRError::Serialization`] if it is not valid URL-safe base64 or does
+/// not decode to exactly 16 bytes.
+pub fn uid_to_bytes(uid: &str) -> Result<[u8; 16], KSMRError> {
+    let decoded = url_safe_str_to_bytes(uid)?;
+    decoded.try_into().map_err(|bytes: Vec<u8>| {
+        KSMRError::Serialization(format!("uid must decode to 16 bytes, got {}", bytes.len()))
+    })
+}
+
+pub fn dict_to_json<T: serde::Serialize>(value: &T) -> Result<String, KSMRError> {
+    serde_json::to_string(value).map_err(|e| KSMRError::Serialization(e.to_string()))
+}
+
+pub fn json_to_dict<T: serde::de::DeserializeOwned>(json_str: &str) -> Result<T, KSMRError> {
+    serde_json::from_str(json_str).map_err(|e| KSMRError::Serialization(e.to_string()))
+}
+
+pub fn now_milliseconds() -> u128 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock is before the Unix epoch")
+        .as_millis()
+}
+
+/// Seam for supplying the current time to TOTP and expiry calculations, so
+/// tests can drive them from a fixed instant instead of the system clock.
+pub trait Clock: Send + Sync {
+    fn now_unix_seconds(&self) -> u64;
+}
+
+/// Default [`Clock`] backed by the system clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_unix_seconds(&self) -> u64 {
+        (now_milliseconds() / 1000) as u64
+    }
+}
+
+/// Returns the number of seconds until `expires_on` (a Unix timestamp in
+/// milliseconds, as stored in `SecretsManagerResponse::expires_on`), using the
+/// given [`Clock`]. Negative once `expires_on` is in the past.
+pub fn seconds_until_expiry_with(clock: &dyn Clock, expires_on_millis: i64) -> i64 {
+    expires_on_millis / 1000 - clock.now_unix_seconds() as i64
+}
+
+/// Returns the number of seconds until `expires_on_millis`, using the system clock.
+pub fn seconds_until_expiry(expires_on_millis: i64) -> i64 {
+    seconds_until_expiry_with(&SystemClock, expires_on_millis)
+}
+
+/// A generated TOTP code, plus how long it (and the period it belongs to) last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TotpCode {
+    pub code: String,
+    pub time_left: u64,
+    pub period: u64,
+}
+
+/// HMAC algorithm used to derive a TOTP code, selected via the `algorithm`
+/// query parameter of an `otpauth://` URI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TotpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+fn base32_decode(input: &str) -> Result<Vec<u8>, KSMRError> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let cleaned = input.trim_end_matches('=').to_ascii_uppercase();
+
+    let mut bits: u64 = 0;
+    let mut bit_count: u32 = 0;
+    let mut out = Vec::new();
+    for c in cleaned.bytes() {
+        let value = ALPHABET
+            .iter()
+            .position(|&b| b == c)
+            .ok_or_else(|| KSMRError::Other(format!("invalid base32 character '{}'", c as char)))?;
+        bits = (bits << 5) | value as u64;
+        bit_count += 5;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            out.push(((bits >> bit_count) & 0xff) as u8);
+        }
+    }
+    Ok(out)
+}
+
+fn totp_hmac_digest(algorithm: TotpAlgorithm, key: &[u8], msg: &[u8]) -> Vec<u8> {
+    use hmac::{Hmac, Mac};
+    match algorithm {
+        TotpAlgorithm::Sha1 => {
+            let mut mac = Hmac::<sha1::Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+            mac.update(msg);
+            mac.finalize().into_bytes().to_vec()
+        }
+        TotpAlgorithm::Sha256 => {
+            let mut mac = Hmac::<sha2::Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+            mac.update(msg);
+            mac.finalize().into_bytes().to_vec()
+        }
+        TotpAlgorithm::Sha512 => {
+            let mut mac = Hmac::<sha2::Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+            mac.update(msg);
+            mac.finalize().into_bytes().to_vec()
+        }
+    }
+}
+
+/// Parses an `otpauth://totp/...` URI and generates the current TOTP code for
+/// it, using the given [`Clock`] in place of the system clock.
+pub fn get_totp_code_with(clock: &dyn Clock, url: &str) -> Result<TotpCode, KSMRError> {
+    let rest = url
+        .strip_prefix("otpauth://totp/")
+        .ok_or_else(|| KSMRError::Other("not a valid otpauth://totp URI".into()))?;
+    let query = rest.split('?').nth(1).unwrap_or("");
+
+    let mut secret: Option<String> = None;
+    let mut algorithm = TotpAlgorithm::Sha1;
+    let mut digits: u32 = 6;
+    let mut period: u64 = 30;
+
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let mut parts = pair.splitn(2, '=');
+        let key = parts.next().unwrap_or("");
+        let value = parts.next().unwrap_or("");
+        match key {
+            "secret" => secret = Some(value.to_string()),
+            "algorithm" => {
+                algorithm = match value.to_ascii_uppercase().as_str() {
+                    "SHA1" => TotpAlgorithm::Sha1,
+                    "SHA256" => TotpAlgorithm::Sha256,
+                    "SHA512" => TotpAlgorithm::Sha512,
+                    other => {
+                        return Err(KSMRError::Other(format!(
+                            "invalid value '{other}' for TOTP algorithm, must be SHA1, SHA256 or SHA512"
+                        )))
+                    }
+                }
+            }
+            "digits" => digits = value.parse().unwrap_or(digits),
+            "period" => period = value.parse().unwrap_or(period),
+            _ => {}
+        }
+    }
+
+    let secret = secret.ok_or_else(|| KSMRError::Other("TOTP secret not found in URI".into()))?;
+    if !matches!(digits, 6..=8) {
+        return Err(KSMRError::Other("TOTP digits may only be 6, 7, or 8".into()));
+    }
+
+    let now = clock.now_unix_seconds();
+    let counter = now / period;
+    let key = base32_decode(&secret)?;
+    let digest = totp_hmac_digest(algorithm, &key, &counter.to_be_bytes());
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let code_bytes = [digest[offset] & 0x7f, digest[offset + 1], digest[offset + 2], digest[offset + 3]];
+    let code_int = u32::from_be_bytes(code_bytes);
+    let code = format!("{:0width$}", code_int % 10u32.pow(digits), width = digits as usize);
+
+    let elapsed = now % period;
+    Ok(TotpCode { code, time_left: period - elapsed, period })
+}
+
+/// Generates the current TOTP code for `url`, using the system clock.
+pub fn get_totp_code(url: &str) -> Result<TotpCode, KSMRError> {
+    get_totp_code_with(&SystemClock, url)
+}
+
+/// Retained for call sites that don't need the seam - prefer the `_with` variants in new code.
+pub fn generate_random_bytes_default(length: usize) -> Vec<u8> {
+    generate_random_bytes(length)
+}
+
+const LOWERCASE: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"0123456789";
+const SPECIAL_CHARACTERS: &[u8] = br#""!@#$%()+;<>=?[]{}^.,'"#;
+
+/// Visually ambiguous characters excluded by [`PasswordComplexity::human_friendly`]:
+/// `0`/`O`, `1`/`l`/`I`, and `5`/`S`.
+const AMBIGUOUS_CHARACTERS: &[u8] = b"0O1lI5S";
+
+/// Desired makeup of a generated password. If every category count is `0`,
+/// [`generate_password`] splits `length` evenly across the four categories
+/// (with any remainder going to `special_characters`), matching the Python
+/// core SDK's `generate_password` defaults.
+#[derive(Debug, Clone, Copy)]
+pub struct PasswordComplexity {
+    pub length: usize,
+    pub lowercase: usize,
+    pub uppercase: usize,
+    pub digits: usize,
+    pub special_characters: usize,
+    /// Excludes visually ambiguous characters (`0`/`O`, `1`/`l`/`I`, `5`/`S`)
+    /// from every category, and never starts the password with a special
+    /// character, so a user reading it aloud or typing it from a screenshot
+    /// is less likely to transcribe it wrong. See
+    /// [`PasswordComplexity::human_friendly`] for a ready-made preset.
+    pub exclude_ambiguous: bool,
+}
+
+impl Default for PasswordComplexity {
+    fn default() -> Self {
+        Self {
+            length: 64,
+            lowercase: 0,
+            uppercase: 0,
+            digits: 0,
+            special_characters: 0,
+            exclude_ambiguous: false,
+        }
+    }
+}
+
+impl PasswordComplexity {
+    /// A 16-character preset for passwords a user has to read aloud or type
+    /// by hand: excludes visually ambiguous characters and never starts with
+    /// a special character. Category counts are left at `0`, so
+    /// [`generate_password`] still splits `length` evenly across the four
+    /// categories the same way the default preset does.
+    pub fn human_friendly() -> Self {
+        Self { length: 16, exclude_ambiguous: true, ..Self::default() }
+    }
+}
+
+fn random_sample_with(rng: &dyn RngProvider, count: usize, alphabet: &[u8]) -> Vec<u8> {
+    let indices = generate_random_bytes_with(rng, count);
+    indices.into_iter().map(|b| alphabet[(b as usize) % alphabet.len()]).collect()
+}
+
+/// `alphabet` with every byte in [`AMBIGUOUS_CHARACTERS`] removed.
+fn without_ambiguous(alphabet: &[u8]) -> Vec<u8> {
+    alphabet.iter().copied().filter(|c| !AMBIGUOUS_CHARACTERS.contains(c)).collect()
+}
+
+/// Generates a password according to `complexity`, using the given randomness source.
+pub fn generate_password_with(rng: &dyn RngProvider, complexity: PasswordComplexity) -> String {
+    let mut complexity = complexity;
+    if complexity.length == 0 {
+        complexity.length = 64;
+    }
+    if complexity.lowercase == 0
+        && complexity.uppercase == 0
+        && complexity.digits == 0
+        && complexity.special_characters == 0
+    {
+        let increment = complexity.length / 4;
+        let last_increment = increment + (complexity.length % 4);
+        complexity.lowercase = increment;
+        complexity.uppercase = increment;
+        complexity.digits = increment;
+        complexity.special_characters = last_increment;
+    }
+
+    let (lowercase, uppercase, digits, special_characters) = if complexity.exclude_ambiguous {
+        (
+            without_ambiguous(LOWERCASE),
+            without_ambiguous(UPPERCASE),
+            without_ambiguous(DIGITS),
+            without_ambiguous(SPECIAL_CHARACTERS),
+        )
+    } else {
+        (LOWERCASE.to_vec(), UPPERCASE.to_vec(), DIGITS.to_vec(), SPECIAL_CHARACTERS.to_vec())
+    };
+
+    let mut chars = Vec::with_capacity(complexity.length);
+    chars.extend(random_sample_with(rng, complexity.lowercase, &lowercase));
+    chars.extend(random_sample_with(rng, complexity.uppercase, &uppercase));
+    chars.extend(random_sample_with(rng, complexity.digits, &digits));
+    chars.extend(random_sample_with(rng, complexity.special_characters, &special_characters));
+
+    // Fisher-Yates shuffle so characters from the same category aren't grouped together.
+    let shuffle_entropy = generate_random_bytes_with(rng, chars.len());
+    for i in (1..chars.len()).rev() {
+        let j = (shuffle_entropy[i] as usize) % (i + 1);
+        chars.swap(i, j);
+    }
+
+    if complexity.exclude_ambiguous && !chars.is_empty() && SPECIAL_CHARACTERS.contains(&chars[0]) {
+        // Never start with a special character: if the shuffle landed one up
+        // front, swap it with the first non-special character instead (if
+        // there is one - an all-special-characters password has nothing to
+        // swap with).
+        if let Some(swap_with) = (1..chars.len()).find(|&i| !SPECIAL_CHARACTERS.contains(&chars[i])) {
+            chars.swap(0, swap_with);
+        }
+    }
+
+    // All sampled bytes come from ASCII alphabets, so this can't fail.
+    String::from_utf8(chars).expect("password alphabet is always ASCII")
+}
+
+/// Generates a password according to `complexity`, using the OS CSPRNG.
+pub fn generate_password(complexity: PasswordComplexity) -> String {
+    generate_password_with(&OsRngProvider, complexity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ZeroRng;
+    impl RngProvider for ZeroRng {
+        fn fill_bytes(&self, buf: &mut [u8]) {
+            buf.fill(0);
+        }
+    }
+
+    #[test]
+    fn generate_uid_with_deterministic_rng_is_stable() {
+        assert_eq!(generate_uid_with(&ZeroRng), "AAAAAAAAAAAAAAAAAAAAAA");
+    }
+
+    #[test]
+    fn generate_uid_produces_22_char_url_safe_strings() {
+        let uid = generate_uid();
+        assert_eq!(uid.len(), 22);
+        assert!(uid.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    }
+
+    #[test]
+    fn uid_from_bytes_and_uid_to_bytes_round_trip() {
+        let bytes = [7u8; 16];
+        let uid = uid_from_bytes(&bytes);
+        assert_eq!(uid_to_bytes(&uid).unwrap(), bytes);
+    }
+
+    #[test]
+    fn uid_to_bytes_rejects_the_wrong_length() {
+        let short = bytes_to_url_safe_str(&[1u8; 8]);
+        assert!(uid_to_bytes(&short).is_err());
+    }
+
+    #[test]
+    fn uid_to_bytes_rejects_invalid_base64() {
+        assert!(uid_to_bytes("not valid base64!!").is_err());
+    }
+
+    #[test]
+    fn base64_round_trips() {
+        let data = b"some bytes \x00\x01\x02";
+        let encoded = bytes_to_base64(data);
+        assert_eq!(base64_to_bytes(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        let data: Vec<u8> = vec![0x00, 0x01, 0x02, 0xff, 0xab];
+        let encoded = bytes_to_hex(&data);
+        assert_eq!(encoded, "000102ffab");
+        assert_eq!(hex_to_bytes(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn hex_to_bytes_is_case_insensitive_and_accepts_an_0x_prefix() {
+        assert_eq!(hex_to_bytes("DEADBEEF").unwrap(), vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(hex_to_bytes("0xdeadbeef").unwrap(), vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(hex_to_bytes("0XDEADBEEF").unwrap(), vec![0xde, 0xad, 0xbe, 0xef]);
+    }
+
+    #[test]
+    fn hex_to_bytes_rejects_odd_length_and_non_hex_input() {
+        assert!(hex_to_bytes("abc").is_err());
+        assert!(hex_to_bytes("zz").is_err());
+    }
+
+    #[test]
+    fn generate_password_honors_explicit_complexity() {
+        let complexity = PasswordComplexity {
+            length: 12,
+            lowercase: 4,
+            uppercase: 4,
+            digits: 2,
+            special_characters: 2,
+            ..PasswordComplexity::default()
+        };
+        let password = generate_password(complexity);
+        assert_eq!(password.len(), 12);
+        assert_eq!(password.chars().filter(|c| c.is_ascii_lowercase()).count(), 4);
+        assert_eq!(password.chars().filter(|c| c.is_ascii_uppercase()).count(), 4);
+        assert_eq!(password.chars().filter(|c| c.is_ascii_digit()).count(), 2);
+    }
+
+    #[test]
+    fn generate_password_splits_default_length_evenly() {
+        let password = generate_password(PasswordComplexity::default());
+        assert_eq!(password.len(), 64);
+    }
+
+    #[test]
+    fn human_friendly_password_excludes_ambiguous_characters() {
+        for _ in 0..20 {
+            let password = generate_password(PasswordComplexity::human_friendly());
+            assert_eq!(password.len(), 16);
+            assert!(
+                password.chars().all(|c| !AMBIGUOUS_CHARACTERS.contains(&(c as u8))),
+                "password contained an ambiguous character: {password}"
+            );
+        }
+    }
+
+    #[test]
+    fn human_friendly_password_never_starts_with_a_special_character() {
+        for _ in 0..20 {
+            let password = generate_password(PasswordComplexity::human_friendly());
+            let first = password.chars().next().unwrap();
+            assert!(!SPECIAL_CHARACTERS.contains(&(first as u8)), "password started with '{first}': {password}");
+        }
+    }
+
+    #[test]
+    fn bytes_to_string_reports_a_key_mismatch_hint_on_invalid_utf8() {
+        let err = bytes_to_string(&[0xff, 0xfe, 0xfd]).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("key mismatch"), "{message}");
+        assert!(message.contains("fffefd"), "{message}");
+    }
+
+    #[test]
+    fn bytes_to_string_lossy_replaces_invalid_bytes_instead_of_erroring() {
+        let mut bytes = b"before-".to_vec();
+        bytes.extend_from_slice(&[0xff, 0xfe]);
+        bytes.extend_from_slice(b"-after");
+
+        let result = bytes_to_string_lossy(&bytes);
+        assert!(result.starts_with("before-"));
+        assert!(result.ends_with("-after"));
+        assert!(result.contains('\u{FFFD}'));
+    }
+
+    struct FixedClock(u64);
+    impl Clock for FixedClock {
+        fn now_unix_seconds(&self) -> u64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn totp_code_matches_known_rfc6238_vector() {
+        // RFC 6238 SHA1 test vector at T=59s: secret "12345678901234567890" (ASCII),
+        // base32 encoded below, expected code "287082".
+        let clock = FixedClock(59);
+        let totp = get_totp_code_with(
+            &clock,
+            "otpauth://totp/Example?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&digits=6&period=30",
+        )
+        .unwrap();
+        assert_eq!(totp.code, "287082");
+        assert_eq!(totp.period, 30);
+        assert_eq!(totp.time_left, 1);
+    }
+
+    #[test]
+    fn totp_code_is_deterministic_for_a_fixed_clock() {
+        let clock = FixedClock(1_700_000_000);
+        let url = "otpauth://totp/Example?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+        let first = get_totp_code_with(&clock, url).unwrap();
+        let second = get_totp_code_with(&clock, url).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn totp_code_rejects_missing_secret() {
+        let clock = FixedClock(0);
+        assert!(get_totp_code_with(&clock, "otpauth://totp/Example?digits=6").is_err());
+    }
+
+    #[test]
+    fn seconds_until_expiry_is_driven_by_the_clock() {
+        let clock = FixedClock(1_000);
+        assert_eq!(seconds_until_expiry_with(&clock, 5_000_000), 4_000);
+        assert_eq!(seconds_until_expiry_with(&clock, 500_000), -500);
+    }
+}